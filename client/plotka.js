@@ -0,0 +1,356 @@
+// The built-in Plotka client.
+//
+// Keeps the records of every dataset received over WS and draws the numeric
+// fields of the chosen dataset against the chosen x field.
+
+(function () {
+    'use strict';
+
+    var COLORS = [
+        '#1f77b4', '#ff7f0e', '#2ca02c', '#d62728', '#9467bd',
+        '#8c564b', '#e377c2', '#7f7f7f', '#bcbd22', '#17becf',
+    ];
+    var PREFERRED_X_FIELDS = ['t', 'time', 'timestamp', 'x'];
+    var INDEX = '(index)';
+    var PADDING = { top: 16, right: 16, bottom: 32, left: 64 };
+    var RECONNECT_DELAY = 1000;
+
+    var datasets = {};
+    // Records of the datasets being initialized, by dataset.
+    var pending = {};
+    var current = null;
+    var xField = INDEX;
+    var xFieldChosen = false;
+    var hidden = {};
+    var redrawRequested = false;
+    var isShutDown = false;
+    // The sequence number of the last received record and the instance of
+    // the server it comes from, sent when reconnecting to receive only the
+    // missed records.
+    var lastSeq = null;
+    var instance = null;
+
+    var canvas = document.getElementById('chart');
+    var datasetSelect = document.getElementById('dataset');
+    var xFieldSelect = document.getElementById('x-field');
+    var fieldsSpan = document.getElementById('fields');
+    var status = document.getElementById('status');
+
+    function getDataset(name) {
+        if (!datasets[name]) {
+            datasets[name] = { records: [], fields: [], labels: {} };
+        }
+
+        return datasets[name];
+    }
+
+    function pushRecord(dataset, record) {
+        var changed = false;
+
+        for (var field in record) {
+            if (dataset.fields.indexOf(field) < 0) {
+                dataset.fields.push(field);
+                changed = true;
+            }
+        }
+        dataset.records.push(record);
+
+        return changed;
+    }
+
+    function handleMessage(message) {
+        var params = message.params;
+        var dataset;
+        var changed = false;
+
+        if (message.method === 'serverInfo') {
+            // The server has been restarted. It sends all the records again.
+            if (instance !== null && params.instance !== instance) {
+                datasets = {};
+                pending = {};
+                current = null;
+                lastSeq = null;
+                updateControls();
+                requestRedraw();
+            }
+            instance = params.instance;
+            return;
+        } else if (message.method === 'initStorageChunk') {
+            if (params.offset === 0) {
+                pending[params.dataset] = [];
+            }
+            Array.prototype.push.apply(pending[params.dataset], params.data);
+            return;
+        } else if (message.method === 'initStorageDone') {
+            dataset = getDataset(params.dataset);
+            dataset.records = [];
+            dataset.fields = [];
+            dataset.labels = params.labels || {};
+            (pending[params.dataset] || []).forEach(function (record) {
+                pushRecord(dataset, record);
+            });
+            delete pending[params.dataset];
+            changed = true;
+            if (params.seq !== null && (lastSeq === null || params.seq > lastSeq)) {
+                lastSeq = params.seq;
+            }
+        } else if (message.method === 'pushRecord') {
+            dataset = getDataset(params.dataset);
+            if (params.labels) {
+                dataset.labels = params.labels;
+                changed = true;
+            }
+            changed = pushRecord(dataset, params.record) || changed;
+            lastSeq = params.seq;
+        } else if (message.method === 'streamEnded') {
+            status.textContent = 'Stream ended';
+            return;
+        } else if (message.method === 'serverShutdown') {
+            isShutDown = true;
+            status.textContent = 'Server shut down';
+            return;
+        } else {
+            return;
+        }
+
+        if (current === null) {
+            current = params.dataset;
+            changed = true;
+        }
+        if (changed) {
+            updateControls();
+        }
+        if (params.dataset === current) {
+            requestRedraw();
+        }
+    }
+
+    function updateControls() {
+        var names = Object.keys(datasets).sort();
+
+        datasetSelect.innerHTML = '';
+        names.forEach(function (name) {
+            var option = new Option(name, name, false, name === current);
+            var labels = datasets[name].labels;
+
+            option.title = Object.keys(labels).map(function (label) {
+                return label + '=' + labels[label];
+            }).join(', ');
+            datasetSelect.add(option);
+        });
+
+        var fields = current === null ? [] : datasets[current].fields.slice();
+        fields.sort();
+
+        if (xField !== INDEX && fields.indexOf(xField) < 0) {
+            xField = INDEX;
+        }
+        if (xField === INDEX && !xFieldChosen) {
+            PREFERRED_X_FIELDS.some(function (field) {
+                if (fields.indexOf(field) >= 0) {
+                    xField = field;
+                    return true;
+                }
+                return false;
+            });
+        }
+
+        xFieldSelect.innerHTML = '';
+        [INDEX].concat(fields).forEach(function (field) {
+            xFieldSelect.add(new Option(field, field, false, field === xField));
+        });
+
+        fieldsSpan.innerHTML = '';
+        yFields().forEach(function (field, i) {
+            var label = document.createElement('label');
+            var checkbox = document.createElement('input');
+
+            checkbox.type = 'checkbox';
+            checkbox.checked = !hidden[field];
+            checkbox.addEventListener('change', function () {
+                hidden[field] = !checkbox.checked;
+                requestRedraw();
+            });
+            label.style.color = COLORS[i % COLORS.length];
+            label.appendChild(checkbox);
+            label.appendChild(document.createTextNode(field));
+            fieldsSpan.appendChild(label);
+        });
+    }
+
+    function yFields() {
+        if (current === null) {
+            return [];
+        }
+
+        return datasets[current].fields
+            .filter(function (field) {
+                return field !== xField;
+            })
+            .sort();
+    }
+
+    function requestRedraw() {
+        if (!redrawRequested) {
+            redrawRequested = true;
+            window.requestAnimationFrame(function () {
+                redrawRequested = false;
+                draw();
+            });
+        }
+    }
+
+    function isNumber(value) {
+        return typeof value === 'number' && isFinite(value);
+    }
+
+    function formatNumber(value) {
+        return Math.abs(value) >= 1e5 || (value !== 0 && Math.abs(value) < 1e-3)
+            ? value.toExponential(2)
+            : String(Math.round(value * 1000) / 1000);
+    }
+
+    function draw() {
+        var ratio = window.devicePixelRatio || 1;
+        var width = canvas.clientWidth;
+        var height = canvas.clientHeight;
+
+        canvas.width = width * ratio;
+        canvas.height = height * ratio;
+
+        var ctx = canvas.getContext('2d');
+        ctx.scale(ratio, ratio);
+        ctx.clearRect(0, 0, width, height);
+
+        if (current === null) {
+            return;
+        }
+
+        var records = datasets[current].records;
+        var fields = yFields().map(function (field, i) {
+            return { name: field, color: COLORS[i % COLORS.length] };
+        }).filter(function (field) {
+            return !hidden[field.name];
+        });
+        var x = function (record, i) {
+            return xField === INDEX ? i : record[xField];
+        };
+
+        // Draw at most one point per horizontal pixel.
+        var plotWidth = width - PADDING.left - PADDING.right;
+        var plotHeight = height - PADDING.top - PADDING.bottom;
+        var step = Math.max(1, Math.floor(records.length / plotWidth));
+
+        var xMin = Infinity, xMax = -Infinity;
+        var yMin = Infinity, yMax = -Infinity;
+        for (var i = 0; i < records.length; i += step) {
+            var xValue = x(records[i], i);
+            if (!isNumber(xValue)) {
+                continue;
+            }
+            xMin = Math.min(xMin, xValue);
+            xMax = Math.max(xMax, xValue);
+            fields.forEach(function (field) {
+                var yValue = records[i][field.name];
+                if (isNumber(yValue)) {
+                    yMin = Math.min(yMin, yValue);
+                    yMax = Math.max(yMax, yValue);
+                }
+            });
+        }
+
+        if (xMin > xMax || yMin > yMax) {
+            return;
+        }
+        if (xMin === xMax) {
+            xMin -= 1;
+            xMax += 1;
+        }
+        if (yMin === yMax) {
+            yMin -= 1;
+            yMax += 1;
+        }
+
+        var scaleX = function (value) {
+            return PADDING.left + (value - xMin) / (xMax - xMin) * plotWidth;
+        };
+        var scaleY = function (value) {
+            return PADDING.top + (yMax - value) / (yMax - yMin) * plotHeight;
+        };
+
+        ctx.strokeStyle = '#ccc';
+        ctx.strokeRect(PADDING.left, PADDING.top, plotWidth, plotHeight);
+
+        ctx.fillStyle = '#666';
+        ctx.font = '12px sans-serif';
+        ctx.textAlign = 'right';
+        ctx.fillText(formatNumber(yMax), PADDING.left - 4, PADDING.top + 12);
+        ctx.fillText(formatNumber(yMin), PADDING.left - 4, PADDING.top + plotHeight);
+        ctx.fillText(formatNumber(xMax), PADDING.left + plotWidth, height - 12);
+        ctx.textAlign = 'left';
+        ctx.fillText(formatNumber(xMin), PADDING.left, height - 12);
+        ctx.textAlign = 'center';
+        ctx.fillText(xField, PADDING.left + plotWidth / 2, height - 12);
+
+        fields.forEach(function (field) {
+            var drawing = false;
+
+            ctx.strokeStyle = field.color;
+            ctx.beginPath();
+            for (var i = 0; i < records.length; i += step) {
+                var xValue = x(records[i], i);
+                var yValue = records[i][field.name];
+
+                // Missing values leave gaps in the line.
+                if (!isNumber(xValue) || !isNumber(yValue)) {
+                    drawing = false;
+                    continue;
+                }
+                if (drawing) {
+                    ctx.lineTo(scaleX(xValue), scaleY(yValue));
+                } else {
+                    ctx.moveTo(scaleX(xValue), scaleY(yValue));
+                    drawing = true;
+                }
+            }
+            ctx.stroke();
+        });
+    }
+
+    function connect() {
+        var protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
+        var url = protocol + '//' + window.location.host + '/ws/';
+        if (lastSeq !== null) {
+            url += '?since=' + lastSeq + '&instance=' + instance;
+        }
+        var socket = new WebSocket(url);
+
+        socket.onopen = function () {
+            status.textContent = 'Connected';
+        };
+        socket.onmessage = function (event) {
+            handleMessage(JSON.parse(event.data));
+        };
+        socket.onclose = function () {
+            if (!isShutDown) {
+                status.textContent = 'Disconnected';
+                window.setTimeout(connect, RECONNECT_DELAY);
+            }
+        };
+    }
+
+    datasetSelect.addEventListener('change', function () {
+        current = datasetSelect.value;
+        updateControls();
+        requestRedraw();
+    });
+    xFieldSelect.addEventListener('change', function () {
+        xField = xFieldSelect.value;
+        xFieldChosen = true;
+        updateControls();
+        requestRedraw();
+    });
+    window.addEventListener('resize', requestRedraw);
+
+    connect();
+})();
@@ -1,30 +1,129 @@
-use ::serde_json::*;
+use ::serde::{ser::SerializeMap, Serialize, Serializer};
+use ::serde_json::{self as json, json, Map, Value};
 
-use crate::{parse::record::Record, storage::Storage};
+use std::collections::HashMap;
 
-pub fn compose_push_record_message(record: &Record) -> Value {
-    json!({
+use crate::{
+    parse::record::Record,
+    server::IngestReport,
+    storage::{Labels, Storage, StorageChunk},
+};
+
+/// Compose a message with a new record. The labels of the dataset are sent
+/// along with its first record.
+pub fn compose_push_record_message(
+    dataset: &str,
+    record: &Record,
+    seq: u64,
+    labels: Option<&Labels>,
+) -> Value {
+    let mut message = json!({
          "method": "pushRecord",
          "params": {
+             "dataset": dataset,
              "record": record,
+             "seq": seq,
+         }
+    });
+
+    if let Some(labels) = labels.filter(|labels| !labels.is_empty()) {
+        message["params"]["labels"] = json!(labels);
+    }
+
+    message
+}
+
+/// Compose a message with a chunk of the records of the dataset.
+///
+/// The message is serialized straight from the columns of the storage.
+pub fn compose_init_chunk_message(
+    dataset: &str,
+    chunk: &StorageChunk,
+) -> json::Result<String> {
+    struct Params<'a, 'b>(&'a str, &'a StorageChunk<'b>);
+
+    impl<'a, 'b> Serialize for Params<'a, 'b> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut map = serializer.serialize_map(Some(3))?;
+            map.serialize_entry("dataset", self.0)?;
+            map.serialize_entry("offset", &self.1.offset())?;
+            map.serialize_entry("data", self.1)?;
+            map.end()
+        }
+    }
+
+    struct Message<'a, 'b>(Params<'a, 'b>);
+
+    impl<'a, 'b> Serialize for Message<'a, 'b> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry("method", "initStorageChunk")?;
+            map.serialize_entry("params", &self.0)?;
+            map.end()
+        }
+    }
+
+    json::to_string(&Message(Params(dataset, chunk)))
+}
+
+/// Compose the message sent after all the chunks of the dataset.
+pub fn compose_init_done_message(dataset: &str, storage: &Storage) -> Value {
+    json!({
+         "method": "initStorageDone",
+         "params": {
+             "dataset": dataset,
+             "records": storage.records_len(),
+             "seq": storage.last_seq(),
+             "labels": storage.labels(),
          }
     })
 }
 
-pub fn compose_init_message(storage: &Storage) -> Value {
-    if storage.is_empty() {
-        json!({
-             "method": "initStorage",
-             "params": {
-                 "data": [],
-             }
+/// Compose the message sent once all the inputs have ended, with the numbers
+/// of the records in the datasets and the totals of the ingested ones.
+pub fn compose_stream_ended_message(
+    totals: &IngestReport,
+    storages: &HashMap<String, Storage>,
+) -> Value {
+    let datasets = storages
+        .iter()
+        .map(|(dataset, storage)| {
+            (dataset.clone(), json!({ "records": storage.records_len() }))
         })
-    } else {
-        json!({
-             "method": "initStorage",
-             "params": {
-                 "data": storage,
-             }
-        })
-    }
+        .collect::<Map<_, _>>();
+
+    json!({
+         "method": "streamEnded",
+         "params": {
+             "accepted": totals.accepted,
+             "filtered": totals.filtered,
+             "rejected": totals.rejected,
+             "datasets": datasets,
+         }
+    })
+}
+
+/// Compose the message sent first to every client, with the id of the
+/// instance of the server.
+pub fn compose_server_info_message(instance: &str) -> Value {
+    json!({
+         "method": "serverInfo",
+         "params": {
+             "instance": instance,
+         }
+    })
+}
+
+/// Compose the message sent to every client before the server stops.
+pub fn compose_server_shutdown_message() -> Value {
+    json!({
+         "method": "serverShutdown",
+         "params": {}
+    })
 }
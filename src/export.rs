@@ -0,0 +1,59 @@
+//! Storage export.
+
+use ::arrow::{
+    array::{ArrayRef, Float64Array, Int64Array},
+    datatypes::{DataType, Field, Schema},
+    error::ArrowError,
+    ipc::writer::StreamWriter,
+    record_batch::RecordBatch,
+};
+
+use std::sync::Arc;
+
+use crate::storage::{NumberVec, Storage};
+
+/// Write the storage as an Arrow IPC stream with a single record batch.
+///
+/// The fields are sorted by name. Integer columns are written as `Int64`,
+/// float columns as `Float64`. The labels of the records are stored in the
+/// metadata of the schema.
+pub fn export_arrow(storage: &Storage) -> Result<Vec<u8>, ArrowError> {
+    let mut columns = storage.columns().collect::<Vec<_>>();
+    columns.sort_by_key(|(field, _)| *field);
+
+    let fields = columns
+        .iter()
+        .map(|(field, column)| {
+            let data_type = match column {
+                NumberVec::Float(_) => DataType::Float64,
+                NumberVec::Int(_) => DataType::Int64,
+            };
+
+            Field::new(*field, data_type, false)
+        })
+        .collect::<Vec<_>>();
+    let arrays = columns
+        .iter()
+        .map(|(_, column)| -> ArrayRef {
+            match column {
+                NumberVec::Float(vec) =>
+                    Arc::new(Float64Array::from(vec.clone())),
+                NumberVec::Int(vec) => Arc::new(Int64Array::from(vec.clone())),
+            }
+        })
+        .collect::<Vec<_>>();
+    let metadata = storage
+        .labels()
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    let schema = Arc::new(Schema::new_with_metadata(fields, metadata));
+
+    let mut writer = StreamWriter::try_new(vec![], &schema)?;
+    if !storage.is_empty() {
+        writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+    }
+    writer.finish()?;
+
+    writer.into_inner()
+}
@@ -0,0 +1,760 @@
+//! Expressions evaluated on records.
+//!
+//! An expression can use numbers, the fields of the record, the fields of the
+//! previous record of the same dataset (`prev(field)`), arithmetic operators
+//! (`+`, `-`, `*`, `/`, `%`, `^`), parentheses and common math functions, e.g.
+//! `0.5 * mass * velocity^2` or `x - prev(x)`. Field names which are not valid
+//! identifiers can be quoted with backticks.
+//!
+//! Comparisons (`==`, `!=`, `<`, `<=`, `>`, `>=`) and logical operators (`&&`,
+//! `||`, `!`) evaluate to `1` or `0`. Any number other than `0` and `NaN` is
+//! true.
+
+use std::{collections::HashSet, fmt, str::FromStr};
+
+/// Expression error.
+///
+/// Unlike [`ParseError`][crate::parse::ParseError], it describes the cause
+/// of the error, since the expressions are written by the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprError(String);
+
+impl ExprError {
+    pub(crate) fn new<S: Into<String>>(message: S) -> Self {
+        ExprError(message.into())
+    }
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Values of the fields an expression is evaluated with.
+pub trait Scope {
+    /// Get the value of the field of the current record.
+    fn get(&self, field: &str) -> Option<f64>;
+
+    /// Get the value of the field of the previous record.
+    fn get_previous(&self, field: &str) -> Option<f64>;
+}
+
+/// Binary operator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Rem,
+    /// `^`
+    Pow,
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+}
+
+impl BinaryOp {
+    fn apply(self, lhs: f64, rhs: f64) -> f64 {
+        match self {
+            BinaryOp::Add => lhs + rhs,
+            BinaryOp::Sub => lhs - rhs,
+            BinaryOp::Mul => lhs * rhs,
+            BinaryOp::Div => lhs / rhs,
+            BinaryOp::Rem => lhs % rhs,
+            BinaryOp::Pow => lhs.powf(rhs),
+            BinaryOp::Eq => from_bool(lhs == rhs),
+            BinaryOp::Ne => from_bool(lhs != rhs),
+            BinaryOp::Lt => from_bool(lhs < rhs),
+            BinaryOp::Le => from_bool(lhs <= rhs),
+            BinaryOp::Gt => from_bool(lhs > rhs),
+            BinaryOp::Ge => from_bool(lhs >= rhs),
+            BinaryOp::And => from_bool(is_true(lhs) && is_true(rhs)),
+            BinaryOp::Or => from_bool(is_true(lhs) || is_true(rhs)),
+        }
+    }
+}
+
+/// Check whether the value is true, i.e. neither `0` nor `NaN`.
+pub fn is_true(value: f64) -> bool {
+    value != 0.0 && !value.is_nan()
+}
+
+fn from_bool(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Math function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    /// `abs(x)`
+    Abs,
+    /// `sqrt(x)`
+    Sqrt,
+    /// `exp(x)`
+    Exp,
+    /// `ln(x)`
+    Ln,
+    /// `log2(x)`
+    Log2,
+    /// `log10(x)`
+    Log10,
+    /// `sin(x)`
+    Sin,
+    /// `cos(x)`
+    Cos,
+    /// `tan(x)`
+    Tan,
+    /// `asin(x)`
+    Asin,
+    /// `acos(x)`
+    Acos,
+    /// `atan(x)`
+    Atan,
+    /// `floor(x)`
+    Floor,
+    /// `ceil(x)`
+    Ceil,
+    /// `round(x)`
+    Round,
+    /// `atan2(y, x)`
+    Atan2,
+    /// `hypot(x, y)`
+    Hypot,
+    /// `pow(x, y)`
+    Pow,
+    /// `min(x, y)`
+    Min,
+    /// `max(x, y)`
+    Max,
+}
+
+impl Function {
+    /// Get the function by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let function = match name {
+            "abs" => Function::Abs,
+            "sqrt" => Function::Sqrt,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "log2" => Function::Log2,
+            "log10" => Function::Log10,
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "asin" => Function::Asin,
+            "acos" => Function::Acos,
+            "atan" => Function::Atan,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "round" => Function::Round,
+            "atan2" => Function::Atan2,
+            "hypot" => Function::Hypot,
+            "pow" => Function::Pow,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => return None,
+        };
+
+        Some(function)
+    }
+
+    /// The number of arguments.
+    fn arity(self) -> usize {
+        match self {
+            Function::Atan2
+            | Function::Hypot
+            | Function::Pow
+            | Function::Min
+            | Function::Max => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Function::Abs => args[0].abs(),
+            Function::Sqrt => args[0].sqrt(),
+            Function::Exp => args[0].exp(),
+            Function::Ln => args[0].ln(),
+            Function::Log2 => args[0].log2(),
+            Function::Log10 => args[0].log10(),
+            Function::Sin => args[0].sin(),
+            Function::Cos => args[0].cos(),
+            Function::Tan => args[0].tan(),
+            Function::Asin => args[0].asin(),
+            Function::Acos => args[0].acos(),
+            Function::Atan => args[0].atan(),
+            Function::Floor => args[0].floor(),
+            Function::Ceil => args[0].ceil(),
+            Function::Round => args[0].round(),
+            Function::Atan2 => args[0].atan2(args[1]),
+            Function::Hypot => args[0].hypot(args[1]),
+            Function::Pow => args[0].powf(args[1]),
+            Function::Min => args[0].min(args[1]),
+            Function::Max => args[0].max(args[1]),
+        }
+    }
+}
+
+/// Expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A number literal.
+    Number(f64),
+    /// The value of the field of the current record.
+    Field(String),
+    /// The value of the field of the previous record.
+    Prev(String),
+    /// Negation.
+    Neg(Box<Expr>),
+    /// Logical negation.
+    Not(Box<Expr>),
+    /// A binary operation.
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// A function call.
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    /// Evaluate the expression.
+    ///
+    /// Fields of the previous record evaluate to `NaN` if there is no previous
+    /// record. Missing fields of the current record are an error.
+    pub fn eval<S: Scope>(&self, scope: &S) -> Result<f64, ExprError> {
+        let value = match self {
+            Expr::Number(number) => *number,
+            Expr::Field(field) => scope
+                .get(field)
+                .ok_or_else(|| ExprError(format!("no field `{}`", field)))?,
+            Expr::Prev(field) => scope.get_previous(field).unwrap_or(f64::NAN),
+            Expr::Neg(expr) => -expr.eval(scope)?,
+            Expr::Not(expr) => from_bool(!is_true(expr.eval(scope)?)),
+            // Skip the right-hand side if the left-hand side decides.
+            Expr::Binary(BinaryOp::And, lhs, _)
+                if !is_true(lhs.eval(scope)?) =>
+                0.0,
+            Expr::Binary(BinaryOp::Or, lhs, _) if is_true(lhs.eval(scope)?) =>
+                1.0,
+            Expr::Binary(op, lhs, rhs) =>
+                op.apply(lhs.eval(scope)?, rhs.eval(scope)?),
+            Expr::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                function.apply(&args)
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Get the fields of the previous record the expression refers to.
+    pub fn previous_fields(&self) -> HashSet<&str> {
+        let mut fields = HashSet::new();
+        self.visit(&mut |expr| {
+            if let Expr::Prev(field) = expr {
+                fields.insert(field.as_str());
+            }
+        });
+
+        fields
+    }
+
+    fn visit<'a, F: FnMut(&'a Expr)>(&'a self, f: &mut F) {
+        f(self);
+
+        match self {
+            Expr::Neg(expr) | Expr::Not(expr) => expr.visit(f),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.visit(f);
+                rhs.visit(f);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|arg| arg.visit(f)),
+            _ => {}
+        }
+    }
+}
+
+/// Token of an expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    /// A field name quoted with backticks.
+    Quoted(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Ident(ident) => write!(f, "{}", ident),
+            Token::Quoted(field) => write!(f, "`{}`", field),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+/// Operators, longer ones first.
+const OPERATORS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "%",
+    "^",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, ExprError> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+
+                continue;
+            }
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_digit() || chars[i] == '.')
+                {
+                    i += 1;
+                }
+
+                // The exponent, e.g. `1e-4`.
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    let sign = match chars.get(i + 1) {
+                        Some('+') | Some('-') => 1,
+                        _ => 0,
+                    };
+
+                    if chars.get(i + 1 + sign).is_some_and(char::is_ascii_digit)
+                    {
+                        i += 1 + sign;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+
+                let number = chars[start..i].iter().collect::<String>();
+                let number = number.parse::<f64>().map_err(|_| {
+                    ExprError(format!("invalid number `{}`", number))
+                })?;
+
+                tokens.push(Token::Number(number));
+
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric()
+                        || chars[i] == '_'
+                        || chars[i] == '.')
+                {
+                    i += 1;
+                }
+
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+
+                continue;
+            }
+            '`' => {
+                let start = i + 1;
+                let end = chars[start..]
+                    .iter()
+                    .position(|c| *c == '`')
+                    .map(|len| start + len)
+                    .ok_or_else(|| {
+                        ExprError("unclosed backtick".to_string())
+                    })?;
+                i = end + 1;
+
+                tokens.push(Token::Quoted(chars[start..end].iter().collect()));
+
+                continue;
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            c => {
+                let op = OPERATORS
+                    .iter()
+                    .find(|op| {
+                        op.chars().eq(chars[i..].iter().cloned().take(op.len()))
+                    })
+                    .ok_or_else(|| {
+                        ExprError(format!("unexpected character `{}`", c))
+                    })?;
+                i += op.len();
+
+                tokens.push(Token::Op(op));
+
+                continue;
+            }
+        };
+
+        tokens.push(token);
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser of the expressions.
+struct ExprParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, ExprError> {
+        let token =
+            self.tokens.get(self.position).cloned().ok_or_else(|| {
+                ExprError("unexpected end of expression".to_string())
+            })?;
+        self.position += 1;
+
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), ExprError> {
+        match self.next()? {
+            ref token if token == expected => Ok(()),
+            token => Err(ExprError(format!(
+                "expected `{}`, found `{}`",
+                expected, token
+            ))),
+        }
+    }
+
+    /// Get the next token if it is one of the operators.
+    fn peek_op(&self, ops: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        match self.peek() {
+            Some(Token::Op(c)) =>
+                ops.iter().find(|(op, _)| op == c).map(|(_, op)| *op),
+            _ => None,
+        }
+    }
+
+    /// Parse a sequence of operands joined with the operators.
+    fn parse_binary<F>(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        mut parse_operand: F,
+    ) -> Result<Expr, ExprError>
+    where
+        F: FnMut(&mut Self) -> Result<Expr, ExprError>,
+    {
+        let mut lhs = parse_operand(self)?;
+
+        while let Some(op) = self.peek_op(ops) {
+            self.position += 1;
+
+            let rhs = parse_operand(self)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ExprError> {
+        self.parse_binary(&[("||", BinaryOp::Or)], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ExprError> {
+        self.parse_binary(&[("&&", BinaryOp::And)], Self::parse_comparison)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ExprError> {
+        self.parse_binary(
+            &[
+                ("==", BinaryOp::Eq),
+                ("!=", BinaryOp::Ne),
+                ("<", BinaryOp::Lt),
+                ("<=", BinaryOp::Le),
+                (">", BinaryOp::Gt),
+                (">=", BinaryOp::Ge),
+            ],
+            Self::parse_sum,
+        )
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, ExprError> {
+        self.parse_binary(
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            Self::parse_product,
+        )
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, ExprError> {
+        self.parse_binary(
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+            Self::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ExprError> {
+        match self.peek() {
+            Some(Token::Op("-")) => {
+                self.position += 1;
+
+                Ok(Expr::Neg(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Op("!")) => {
+                self.position += 1;
+
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Op("+")) => {
+                self.position += 1;
+
+                self.parse_unary()
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    /// Parse a power. It is right-associative and binds tighter than the
+    /// unary minus on its left, i.e. `-x^2` is `-(x^2)`.
+    fn parse_power(&mut self) -> Result<Expr, ExprError> {
+        let base = self.parse_atom()?;
+
+        if let Some(Token::Op("^")) = self.peek() {
+            self.position += 1;
+            let exponent = self.parse_unary()?;
+
+            Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, ExprError> {
+        match self.next()? {
+            Token::Number(number) => Ok(Expr::Number(number)),
+            Token::Quoted(field) => Ok(Expr::Field(field)),
+            Token::Ident(name) =>
+                if let Some(Token::LParen) = self.peek() {
+                    self.position += 1;
+
+                    self.parse_call(name)
+                } else {
+                    Ok(Expr::Field(name))
+                },
+            Token::LParen => {
+                let expr = self.parse_or()?;
+                self.expect(&Token::RParen)?;
+
+                Ok(expr)
+            }
+            token => Err(ExprError(format!("unexpected `{}`", token))),
+        }
+    }
+
+    /// Parse the arguments of the function following the opening parenthesis.
+    fn parse_call(&mut self, name: String) -> Result<Expr, ExprError> {
+        let mut args = vec![];
+
+        if let Some(Token::RParen) = self.peek() {
+            self.position += 1;
+        } else {
+            loop {
+                args.push(self.parse_or()?);
+
+                match self.next()? {
+                    Token::Comma => {}
+                    Token::RParen => break,
+                    token =>
+                        return Err(ExprError(format!(
+                            "expected `,` or `)`, found `{}`",
+                            token
+                        ))),
+                }
+            }
+        }
+
+        if name == "prev" {
+            return match args.as_slice() {
+                [Expr::Field(field)] => Ok(Expr::Prev(field.clone())),
+                _ => Err(ExprError(
+                    "`prev` takes a single field name".to_string(),
+                )),
+            };
+        }
+
+        let function = Function::from_name(&name)
+            .ok_or_else(|| ExprError(format!("unknown function `{}`", name)))?;
+
+        if args.len() != function.arity() {
+            return Err(ExprError(format!(
+                "`{}` takes {} argument(s)",
+                name,
+                function.arity()
+            )));
+        }
+
+        Ok(Expr::Call(function, args))
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = ExprParser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+
+        let expr = parser.parse_or()?;
+
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(ExprError(format!("unexpected `{}`", token))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use std::collections::HashMap;
+
+    use super::*;
+
+    struct TestScope {
+        current: HashMap<&'static str, f64>,
+        previous: HashMap<&'static str, f64>,
+    }
+
+    impl Scope for TestScope {
+        fn get(&self, field: &str) -> Option<f64> {
+            self.current.get(field).cloned()
+        }
+
+        fn get_previous(&self, field: &str) -> Option<f64> {
+            self.previous.get(field).cloned()
+        }
+    }
+
+    fn eval(expr: &str) -> Result<f64, ExprError> {
+        let scope = TestScope {
+            current: vec![("mass", 2.0), ("velocity", 3.0), ("a b", 1.5)]
+                .into_iter()
+                .collect(),
+            previous: vec![("velocity", 1.0)].into_iter().collect(),
+        };
+
+        expr.parse::<Expr>()?.eval(&scope)
+    }
+
+    #[test]
+    fn test_eval_arithmetic() {
+        assert_eq!(eval("0.5 * mass * velocity^2"), Ok(9.0));
+        assert_eq!(eval("1 + 2 * 3 - 4 / 2"), Ok(5.0));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(eval("-2^2"), Ok(-4.0));
+        assert_eq!(eval("2^3^2"), Ok(512.0));
+        assert_eq!(eval("7 % 4 + 1e-1 * 10 + 2E+1"), Ok(24.0));
+        assert_eq!(eval("`a b` * 2"), Ok(3.0));
+    }
+
+    #[test]
+    fn test_eval_functions() {
+        assert_eq!(eval("sqrt(abs(-16))"), Ok(4.0));
+        assert_eq!(eval("max(mass, velocity) + min(1, 2)"), Ok(4.0));
+        assert_eq!(eval("round(ln(exp(2)))"), Ok(2.0));
+    }
+
+    #[test]
+    fn test_eval_logic() {
+        assert_eq!(eval("mass < velocity && velocity >= 3"), Ok(1.0));
+        assert_eq!(eval("mass == 2 || x > 0"), Ok(1.0));
+        assert_eq!(eval("!(mass != 2) + (1 <= 0)"), Ok(1.0));
+        assert_eq!(eval("mass > 2 && x > 0"), Ok(0.0));
+        assert_eq!(eval("1 + 1 == 2"), Ok(1.0));
+        assert!(!is_true(f64::NAN));
+    }
+
+    #[test]
+    fn test_eval_previous() {
+        let expr = "velocity - prev(velocity)".parse::<Expr>().unwrap();
+
+        assert_eq!(
+            expr.previous_fields(),
+            vec!["velocity"].into_iter().collect()
+        );
+        assert_eq!(eval("velocity - prev(velocity)"), Ok(2.0));
+        assert!(eval("prev(mass)").unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for expr in &[
+            "",
+            "1 +",
+            "(1",
+            "1 2",
+            "foo(1)",
+            "sqrt(1, 2)",
+            "prev(1)",
+            "`a",
+            "1 $ 2",
+            "x = 1",
+        ] {
+            assert!(expr.parse::<Expr>().is_err(), "{}", expr);
+        }
+
+        assert_eq!(eval("x + 1"), Err(ExprError("no field `x`".to_string())));
+    }
+}
@@ -0,0 +1,599 @@
+//! Input sources.
+
+use ::flate2::read::MultiGzDecoder;
+use ::log::info;
+use ::zstd::stream::read::Decoder as ZstdDecoder;
+
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Read, Seek, SeekFrom},
+    mem,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Maximal size of a UDP datagram.
+const MAX_DATAGRAM_SIZE: usize = 65_536;
+
+/// How long [`FollowReader`] waits before checking the file again.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The line ending each scrape read by [`ScrapeReader`], as in OpenMetrics.
+const SCRAPE_END: &[u8] = b"# EOF\n";
+
+/// A reader the parsers are fed from.
+///
+/// Every source of data (stdin, HTTP request bodies, etc.) is boxed into this
+/// type so that a single [`Server`][crate::server::Server] can handle all of
+/// them with the same [`Parser`][crate::parse::Parser].
+pub type Source = Box<dyn io::Read + Send>;
+
+/// Magic bytes of a gzip stream.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Magic bytes of a zstd frame.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression of the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// Detect the compression from the magic bytes.
+    Auto,
+    /// Not compressed.
+    None,
+    /// Gzip.
+    Gzip,
+    /// Zstandard.
+    Zstd,
+}
+
+impl Compression {
+    /// Names of the variants accepted by [`Compression::from_name`].
+    pub const NAMES: &'static [&'static str] =
+        &["auto", "none", "gzip", "zstd"];
+
+    /// Get the variant by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Compression::Auto),
+            "none" => Some(Compression::None),
+            "gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Wrap the reader in a decoder.
+///
+/// With [`Compression::Auto`] the compression is detected from the first
+/// bytes of the input, see [`AutoDecoder`].
+pub fn decompress(
+    reader: Source,
+    compression: Compression,
+) -> io::Result<Source> {
+    Ok(match compression {
+        Compression::Auto => Box::new(AutoDecoder::new(reader)),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(ZstdDecoder::new(reader)?),
+        Compression::None => reader,
+    })
+}
+
+/// The state of [`AutoDecoder`].
+enum DecoderState {
+    /// The bytes read so far do not tell the compression yet.
+    Detecting { reader: Source, magic: Vec<u8> },
+    /// The compression has been detected.
+    Decoding(Source),
+}
+
+/// A reader detecting the compression of the input on the first read.
+///
+/// No more bytes than a single read of the input returns are waited for,
+/// unless they may start magic bytes. A source sending a short line and then
+/// waiting, e.g. over TCP, is therefore read right away. The detected bytes
+/// are passed to the decoder along with the rest of the input.
+pub struct AutoDecoder {
+    state: DecoderState,
+}
+
+impl AutoDecoder {
+    /// Wrap the reader, detecting the compression on the first read.
+    pub fn new(reader: Source) -> Self {
+        Self {
+            state: DecoderState::Detecting {
+                reader,
+                magic: vec![],
+            },
+        }
+    }
+
+    /// Check whether the bytes may start magic bytes, but are too few to
+    /// tell.
+    fn is_incomplete(magic: &[u8]) -> bool {
+        [GZIP_MAGIC, ZSTD_MAGIC].iter().any(|expected| {
+            magic.len() < expected.len() && expected.starts_with(magic)
+        })
+    }
+
+    /// Wrap the input in the decoder chosen by the magic bytes.
+    fn detect(&mut self) -> io::Result<()> {
+        let state = mem::replace(
+            &mut self.state,
+            DecoderState::Decoding(Box::new(io::empty())),
+        );
+        let (reader, magic) = match state {
+            DecoderState::Detecting { reader, magic } => (reader, magic),
+            DecoderState::Decoding(_) => unreachable!(),
+        };
+
+        let compression = if magic.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if magic.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        };
+        let reader = Box::new(io::Cursor::new(magic).chain(reader));
+
+        self.state = DecoderState::Decoding(decompress(reader, compression)?);
+
+        Ok(())
+    }
+}
+
+impl io::Read for AutoDecoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match &mut self.state {
+                DecoderState::Decoding(reader) => return reader.read(buf),
+                DecoderState::Detecting { reader, magic }
+                    if Self::is_incomplete(magic) =>
+                {
+                    let mut bytes = [0; ZSTD_MAGIC.len()];
+                    let len = reader
+                        .read(&mut bytes[..ZSTD_MAGIC.len() - magic.len()])?;
+
+                    magic.extend_from_slice(&bytes[..len]);
+
+                    if len == 0 {
+                        self.detect()?;
+                    }
+                }
+                DecoderState::Detecting { .. } => self.detect()?,
+            }
+        }
+    }
+}
+
+/// Where the input is read from.
+#[derive(Debug, Clone)]
+pub enum InputSource {
+    /// The standard input.
+    Stdin,
+    /// TCP connections accepted on the address. Each connection is read
+    /// separately.
+    Tcp(String),
+    /// UDP datagrams received on the address.
+    Udp(String),
+    /// A file.
+    File {
+        /// The path of the file.
+        path: PathBuf,
+        /// Whether the file is read like with `tail -f`.
+        follow: bool,
+    },
+    /// Metrics scraped from an HTTP endpoint.
+    Scrape {
+        /// The URL of the endpoint.
+        url: String,
+        /// How often the endpoint is scraped.
+        interval: Duration,
+    },
+}
+
+/// A reader receiving datagrams from a UDP socket.
+///
+/// Empty datagrams are skipped. With `lines` set, each datagram is treated as
+/// a separate line of input and missing trailing newlines are added.
+/// Otherwise the datagrams are passed on untouched, so that binary formats
+/// can carry a record in each of them.
+pub struct UdpReader {
+    socket: UdpSocket,
+    lines: bool,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl UdpReader {
+    /// Create a reader from a socket bound to the address.
+    pub fn bind<A: ToSocketAddrs>(addr: A, lines: bool) -> io::Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+            lines,
+            buffer: vec![],
+            position: 0,
+        })
+    }
+
+    /// Get the address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+impl Read for UdpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            self.buffer.resize(MAX_DATAGRAM_SIZE, 0);
+            let len = self.socket.recv(&mut self.buffer)?;
+            self.buffer.truncate(len);
+            self.position = 0;
+
+            if self.lines
+                && self.buffer.last().is_some_and(|byte| *byte != b'\n')
+            {
+                self.buffer.push(b'\n');
+            }
+        }
+
+        let len = (&self.buffer[self.position..]).read(buf)?;
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+/// A reader waiting for data appended to a file.
+///
+/// After reaching the end of the file, the reader waits until more data is
+/// written instead of returning `Ok(0)`. If the file is truncated, it is read
+/// again from the start. If it is rotated (another file is moved to its
+/// path), the new file is opened.
+pub struct FollowReader {
+    path: PathBuf,
+    file: File,
+    position: u64,
+}
+
+impl FollowReader {
+    /// Open the file at the path.
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        let file = File::open(&path)?;
+
+        Ok(Self {
+            path,
+            file,
+            position: 0,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_a: &Metadata, _b: &Metadata) -> bool {
+    true
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let len = self.file.read(buf)?;
+
+            if len > 0 || buf.is_empty() {
+                self.position += len as u64;
+
+                return Ok(len);
+            }
+
+            // The file might be missing for a moment while it is rotated.
+            match fs::metadata(&self.path) {
+                Ok(metadata)
+                    if !is_same_file(&metadata, &self.file.metadata()?) =>
+                {
+                    self.file = File::open(&self.path)?;
+                    self.position = 0;
+                }
+                Ok(metadata) if metadata.len() < self.position => {
+                    self.file.seek(SeekFrom::Start(0))?;
+                    self.position = 0;
+                }
+                _ => thread::sleep(FOLLOW_INTERVAL),
+            }
+        }
+    }
+}
+
+/// A reader polling an HTTP endpoint, e.g. Prometheus metrics.
+///
+/// The body of each response is followed by an `# EOF` line, unless it already
+/// ends with one. Failed scrapes, including the ones taking longer than the
+/// interval, are skipped.
+pub struct ScrapeReader {
+    url: String,
+    interval: Duration,
+    last_scrape: Option<Instant>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl ScrapeReader {
+    /// Create a reader scraping the URL every `interval`.
+    pub fn new(url: String, interval: Duration) -> Self {
+        Self {
+            url,
+            interval,
+            last_scrape: None,
+            buffer: vec![],
+            position: 0,
+        }
+    }
+
+    fn scrape(&self) -> io::Result<Vec<u8>> {
+        // A hanging endpoint must not delay the next scrapes.
+        let response = ::ureq::get(&self.url)
+            .timeout(self.interval)
+            .call()
+            .map_err(io::Error::other)?;
+        let mut body = vec![];
+        response.into_reader().read_to_end(&mut body)?;
+
+        Ok(body)
+    }
+}
+
+impl Read for ScrapeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            if let Some(last_scrape) = self.last_scrape {
+                let elapsed = last_scrape.elapsed();
+
+                if elapsed < self.interval {
+                    thread::sleep(self.interval - elapsed);
+                }
+            }
+            self.last_scrape = Some(Instant::now());
+
+            match self.scrape() {
+                Ok(body) => {
+                    self.buffer = body;
+                    self.position = 0;
+
+                    if self.buffer.last().is_some_and(|byte| *byte != b'\n') {
+                        self.buffer.push(b'\n');
+                    }
+                    if !self.buffer.ends_with(SCRAPE_END) {
+                        self.buffer.extend_from_slice(SCRAPE_END);
+                    }
+                }
+                Err(_) => info!("Could not scrape {}.", self.url),
+            }
+        }
+
+        let len = (&self.buffer[self.position..]).read(buf)?;
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use ::flate2::{write::GzEncoder, Compression as GzCompression};
+
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_read_udp_datagrams() {
+        let reader = UdpReader::bind("127.0.0.1:0", true).unwrap();
+        let addr = reader.local_addr().unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(b"1,2", addr).unwrap();
+        sender.send_to(b"", addr).unwrap();
+        sender.send_to(b"3,4\n", addr).unwrap();
+
+        let mut lines = BufReader::new(reader).lines();
+
+        assert_eq!(lines.next().unwrap().unwrap(), "1,2");
+        assert_eq!(lines.next().unwrap().unwrap(), "3,4");
+    }
+
+    #[test]
+    fn test_read_binary_udp_datagrams() {
+        let mut reader = UdpReader::bind("127.0.0.1:0", false).unwrap();
+        let addr = reader.local_addr().unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(&[1, 2], addr).unwrap();
+        sender.send_to(&[3, 4], addr).unwrap();
+
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_follow_truncated_file() {
+        let path = std::env::temp_dir()
+            .join(format!("plotka-follow-{}.csv", std::process::id()));
+        fs::write(&path, "1,2\n").unwrap();
+
+        let mut reader = FollowReader::open(&path).unwrap();
+        let mut buf = [0; 16];
+
+        let len = reader.read(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"1,2\n");
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"3,4\n").unwrap();
+
+        let len = reader.read(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"3,4\n");
+
+        fs::write(&path, "5\n").unwrap();
+
+        let len = reader.read(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"5\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    fn read_decompressed(input: Vec<u8>, compression: Compression) -> Vec<u8> {
+        let mut reader =
+            decompress(Box::new(io::Cursor::new(input)), compression).unwrap();
+        let mut output = vec![];
+        reader.read_to_end(&mut output).unwrap();
+
+        output
+    }
+
+    #[test]
+    fn test_detect_gzip() {
+        let mut encoder = GzEncoder::new(vec![], GzCompression::default());
+        encoder.write_all(b"1,2\n3,4\n").unwrap();
+        let input = encoder.finish().unwrap();
+
+        assert_eq!(read_decompressed(input, Compression::Auto), b"1,2\n3,4\n");
+    }
+
+    #[test]
+    fn test_detect_zstd() {
+        let input = ::zstd::encode_all(&b"1,2\n3,4\n"[..], 0).unwrap();
+
+        assert_eq!(read_decompressed(input, Compression::Auto), b"1,2\n3,4\n");
+    }
+
+    #[test]
+    fn test_detect_no_compression() {
+        let input = b"1\n".to_vec();
+
+        assert_eq!(read_decompressed(input, Compression::Auto), b"1\n");
+    }
+
+    /// A reader returning at most one chunk per read and then failing, like
+    /// a connection waiting for more input.
+    struct ChunkReader(Vec<Vec<u8>>);
+
+    impl io::Read for ChunkReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+
+            let len = buf.len().min(self.0[0].len());
+            buf[..len].copy_from_slice(&self.0[0][..len]);
+            self.0[0].drain(..len);
+
+            if self.0[0].is_empty() {
+                self.0.remove(0);
+            }
+
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_detect_on_first_read() {
+        let reader = ChunkReader(vec![b"1\n".to_vec()]);
+        let mut reader =
+            decompress(Box::new(reader), Compression::Auto).unwrap();
+        let mut buf = [0; 16];
+
+        let len = reader.read(&mut buf).unwrap();
+
+        assert_eq!(&buf[..len], b"1\n");
+
+        // The first byte of the gzip magic bytes is not enough to tell.
+        let mut encoder = GzEncoder::new(vec![], GzCompression::default());
+        encoder.write_all(b"1\n").unwrap();
+        let input = encoder.finish().unwrap();
+        let reader =
+            ChunkReader(vec![input[..1].to_vec(), input[1..].to_vec()]);
+        let mut reader =
+            decompress(Box::new(reader), Compression::Auto).unwrap();
+
+        let len = reader.read(&mut buf).unwrap();
+
+        assert_eq!(&buf[..len], b"1\n");
+    }
+
+    #[test]
+    fn test_force_no_compression() {
+        let input = ::zstd::encode_all(&b"1\n"[..], 0).unwrap();
+
+        assert_eq!(read_decompressed(input.clone(), Compression::None), input);
+    }
+
+    #[test]
+    fn test_scrape_http_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/metrics", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).unwrap();
+
+                let body = format!("up 1\nrequests {}", i);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+
+        let reader = ScrapeReader::new(url, Duration::from_millis(10));
+        let lines = BufReader::new(reader)
+            .lines()
+            .take(6)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            lines,
+            vec!["up 1", "requests 0", "# EOF", "up 1", "requests 1", "# EOF"]
+        );
+    }
+
+    #[test]
+    fn test_scrape_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/metrics", listener.local_addr().unwrap());
+
+        // Accept the connection but never respond.
+        thread::spawn(move || {
+            let _streams = listener.incoming().take(1).collect::<Vec<_>>();
+            thread::sleep(Duration::from_secs(10));
+        });
+
+        let reader = ScrapeReader::new(url, Duration::from_millis(100));
+        let start = Instant::now();
+
+        assert!(reader.scrape().is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
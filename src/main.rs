@@ -1,36 +1,72 @@
 #![warn(missing_docs)]
 #![allow(clippy::redundant_closure)]
 #![allow(clippy::new_without_default)]
+#![allow(clippy::too_many_arguments)]
 
 use ::actix::*;
 use ::actix_web::server::HttpServer;
 use ::actix_web::*;
-use ::clap::{App as Clapp, AppSettings as ClappSettings, Arg, SubCommand};
-use ::log::info;
+use ::clap::{
+    App as Clapp,
+    AppSettings as ClappSettings,
+    Arg,
+    Error as ClapError,
+    ErrorKind as ClapErrorKind,
+    SubCommand,
+};
 use ::color_backtrace;
+use ::humantime::parse_duration;
+use ::log::info;
 use ::pretty_env_logger;
+use ::regex::Regex;
+use ::rustls::ServerConfig;
 
 use std::{
-    io::{stdin, Stdin},
+    fs::File,
+    io::stdin,
+    net::TcpListener,
+    path::{self, Path},
     sync::mpsc::{channel, Receiver, Sender},
     thread,
 };
 
 pub mod compose;
+pub mod export;
+pub mod expr;
+pub mod input;
 pub mod parse;
 pub mod prelude;
+pub mod render;
 pub mod server;
 pub mod storage;
+pub mod tls;
+pub mod transform;
 
 use self::prelude::*;
 
-fn run_server<P: Parser<Stdin> + Send>(
-    parser: P,
-    static_path: String,
+/// Settings of the HTTP server.
+struct HttpSettings {
+    static_path: Option<String>,
     ip_addr: String,
+    /// The token required by every route.
+    token: Option<TokenAuth>,
+    /// Serve over HTTPS and WSS with this config.
+    tls: Option<ServerConfig>,
+}
+
+fn run_server<P: Parser<Source> + Send>(
+    parser: P,
+    datasets: Vec<String>,
+    settings: P::Settings,
+    transform: Transform,
+    compression: Compression,
+    http: Option<HttpSettings>,
+    terminal: Option<TerminalView>,
+    render_on_exit: Option<RenderTarget>,
+    exit_on_eof: bool,
 ) -> (
     thread::JoinHandle<()>,
-    Addr<Server<Stdin, P>>,
+    Addr<Server<Source, P>>,
     Sender<StopAppMessage>,
     Receiver<StopAppMessage>,
 ) {
@@ -42,32 +78,95 @@ fn run_server<P: Parser<Stdin> + Send>(
 
         thread::spawn(move || {
             let sys = actix::System::new("Plotka");
-            let addr = Arbiter::start(|_| Server::new(io_thread_tx, parser));
-            let static_path = static_path;
-
+            let addr = Arbiter::start(move |_| {
+                Server::new(
+                    io_thread_tx,
+                    parser,
+                    datasets,
+                    transform,
+                    render_on_exit,
+                    exit_on_eof,
+                )
+            });
+            if let Some(HttpSettings {
+                static_path,
+                ip_addr,
+                token,
+                tls,
+            }) = http
             {
                 let addr = addr.clone();
 
-                HttpServer::new(move || {
-                    let state = WsSessionState::new(addr.clone());
+                let server = HttpServer::new(move || {
+                    let state = WsSessionState::new(
+                        addr.clone(),
+                        settings.clone(),
+                        compression,
+                    );
 
-                    App::with_state(state)
-                        .resource("/", |r| {
-                            r.method(http::Method::GET).f(|_| {
-                                HttpResponse::Found()
-                                    .header("LOCATION", "/static/index.html")
-                                    .finish()
-                            })
-                        })
+                    let mut app = App::with_state(state);
+                    if let Some(token) = &token {
+                        app = app.middleware(token.clone());
+                    }
+
+                    let app = app
                         .resource("/ws/", |r| r.route().f(ws_handshake))
-                        .handler(
-                            "/static/",
-                            fs::StaticFiles::new(static_path.clone()).unwrap(),
-                        )
-                })
-                .bind(ip_addr)
-                .unwrap()
-                .start();
+                        .resource("/events", |r| {
+                            r.method(http::Method::GET).f(get_events)
+                        })
+                        .resource("/api/records", |r| {
+                            r.method(http::Method::POST).f(post_records)
+                        })
+                        .resource("/export.arrow", |r| {
+                            r.method(http::Method::GET).f(get_arrow_export)
+                        })
+                        .resource("/render.svg", |r| {
+                            r.method(http::Method::GET).f(get_svg_render)
+                        })
+                        .resource("/render.png", |r| {
+                            r.method(http::Method::GET).f(get_png_render)
+                        });
+
+                    match &static_path {
+                        Some(static_path) => app
+                            .resource("/", |r| {
+                                r.method(http::Method::GET).f(|_| {
+                                    HttpResponse::Found()
+                                        .header(
+                                            "LOCATION",
+                                            "/static/index.html",
+                                        )
+                                        .finish()
+                                })
+                            })
+                            .handler(
+                                "/static/",
+                                fs::StaticFiles::new(static_path.clone())
+                                    .unwrap(),
+                            ),
+                        None => app
+                            .resource("/", |r| {
+                                r.method(http::Method::GET).f(get_client_index)
+                            })
+                            .resource("/plotka.js", |r| {
+                                r.method(http::Method::GET).f(get_client_script)
+                            }),
+                    }
+                });
+                let server = match tls {
+                    Some(config) => server.bind_rustls(ip_addr, config),
+                    None => server.bind(ip_addr),
+                };
+
+                server
+                    .unwrap()
+                    // The server shuts down on signals by itself.
+                    .disable_signals()
+                    .start();
+            }
+
+            if let Some(view) = terminal {
+                TerminalSession::new(addr.clone(), view).start();
             }
 
             addr_tx.clone().send(addr).unwrap();
@@ -81,24 +180,133 @@ fn run_server<P: Parser<Stdin> + Send>(
     (server_handle, addr, io_thread_tx, io_thread_rx)
 }
 
-fn run_io<P: Parser<Stdin>>(
+fn feed<P: Parser<Source>>(
+    dataset: &str,
+    reader: Source,
+    settings: P::Settings,
+    compression: Compression,
+    addr: &Addr<Server<Source, P>>,
+) {
+    let reader = match decompress(reader, compression) {
+        Ok(reader) => reader,
+        Err(_) => {
+            info!("Could not decompress the input.");
+
+            return;
+        }
+    };
+
+    for input in P::wrap_reader(reader, settings) {
+        match input {
+            Ok(input) => addr.do_send(InputMessage {
+                dataset: dataset.to_string(),
+                input,
+            }),
+            // E.g. an oversized frame. The reader has skipped it.
+            Err(error) if error.is_recoverable() => {
+                info!("Could not read a record.");
+
+                addr.do_send(RejectedInput);
+            }
+            Err(_) => {
+                info!("Could not read the input.");
+
+                break;
+            }
+        }
+    }
+}
+
+fn read_source<P: Parser<Source>>(
+    dataset: String,
+    source: InputSource,
+    settings: P::Settings,
+    compression: Compression,
+    addr: Addr<Server<Source, P>>,
+) {
+    let reader: Source = match source {
+        InputSource::Stdin => Box::new(stdin()),
+        InputSource::Tcp(ip_addr) => {
+            let listener = TcpListener::bind(ip_addr)
+                .expect("cannot bind the TCP listener");
+
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        info!("Accepted a TCP connection.");
+
+                        let dataset = dataset.clone();
+                        let settings = settings.clone();
+                        let addr = addr.clone();
+
+                        thread::spawn(move || {
+                            feed(
+                                &dataset,
+                                Box::new(stream),
+                                settings,
+                                compression,
+                                &addr,
+                            )
+                        });
+                    }
+                    Err(_) => info!("Could not accept a TCP connection."),
+                }
+            }
+
+            return;
+        }
+        InputSource::Udp(ip_addr) => Box::new(
+            UdpReader::bind(ip_addr, P::LINE_BASED)
+                .expect("cannot bind the UDP socket"),
+        ),
+        InputSource::File { path, follow } =>
+            if follow {
+                Box::new(
+                    FollowReader::open(path)
+                        .expect("cannot open the input file"),
+                )
+            } else {
+                Box::new(File::open(path).expect("cannot open the input file"))
+            },
+        InputSource::Scrape { url, interval } =>
+            Box::new(ScrapeReader::new(url, interval)),
+    };
+
+    feed(&dataset, reader, settings, compression, &addr);
+}
+
+fn run_io<P: Parser<Source>>(
+    sources: Vec<(String, InputSource)>,
     settings: P::Settings,
-    addr: Addr<Server<Stdin, P>>,
+    compression: Compression,
+    addr: Addr<Server<Source, P>>,
     from_server_rx: Receiver<StopAppMessage>,
 ) -> thread::JoinHandle<()> {
     thread::spawn(move || {
-        let reader_handle = thread::spawn(move || {
-            let reader = P::wrap_reader(stdin(), settings);
+        let reader_handles = sources
+            .into_iter()
+            .map(|(dataset, source)| {
+                let settings = settings.clone();
+                let addr = addr.clone();
 
-            reader.for_each(|line| {
-                addr.do_send(InputMessage(line.unwrap()));
-            });
+                thread::spawn(move || {
+                    read_source(dataset, source, settings, compression, addr)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Let the server know once all the sources have ended.
+        thread::spawn(move || {
+            for handle in reader_handles {
+                let _ = handle.join();
+            }
+
+            addr.do_send(EndOfInput);
         });
 
         loop {
             if let Ok(_message) = from_server_rx.recv() {
-                info!("Stopping the reader thread.");
-                drop(reader_handle);
+                info!("Stopping the reader threads.");
 
                 break;
             }
@@ -106,16 +314,36 @@ fn run_io<P: Parser<Stdin>>(
     })
 }
 
-fn run_app<P: Parser<Stdin> + Send>(
+fn run_app<P: Parser<Source> + Send>(
     parser: P,
+    sources: Vec<(String, InputSource)>,
     settings: P::Settings,
-    static_path: String,
-    ip_addr: String,
+    transform: Transform,
+    compression: Compression,
+    http: Option<HttpSettings>,
+    terminal: Option<TerminalView>,
+    render_on_exit: Option<RenderTarget>,
+    exit_on_eof: bool,
 ) {
+    let datasets = sources
+        .iter()
+        .map(|(dataset, _)| dataset.clone())
+        .collect::<Vec<_>>();
+
     // run server and IO thread.
-    let (server_handle, server_addr, to_io_tx, from_server_rx) =
-        run_server(parser, static_path, ip_addr);
-    let io_handle = run_io(settings, server_addr, from_server_rx);
+    let (server_handle, server_addr, to_io_tx, from_server_rx) = run_server(
+        parser,
+        datasets,
+        settings.clone(),
+        transform,
+        compression,
+        http,
+        terminal,
+        render_on_exit,
+        exit_on_eof,
+    );
+    let io_handle =
+        run_io(sources, settings, compression, server_addr, from_server_rx);
 
     let _ = server_handle.join();
     // send a stop signal to the stdio thread in case the server hasn't done it.
@@ -123,6 +351,19 @@ fn run_app<P: Parser<Stdin> + Send>(
     let _ = io_handle.join();
 }
 
+/// Split an `--input` value into the dataset and the path of the file.
+///
+/// The part before the first `=` is the dataset only if it contains no path
+/// separator, so `runs/lr=0.1.csv` is read into the default dataset.
+fn split_input(input: &str) -> (&str, &str) {
+    match input.find('=') {
+        Some(index)
+            if index > 0 && !input[..index].contains(path::is_separator) =>
+            (&input[..index], &input[index + 1..]),
+        _ => (DEFAULT_DATASET, input),
+    }
+}
+
 fn main() {
     // <3
     ::color_backtrace::install();
@@ -146,11 +387,231 @@ fn main() {
         )
         .arg(
             Arg::with_name("static-path")
-                .help("Set IP address used to bind the internal server.")
+                .help(
+                    "Serve the client from DIR instead of the built-in one.",
+                )
                 .long("static-path")
                 .short("s")
-                .value_name("DIR")
-                .required(true),
+                .value_name("DIR"),
+        )
+        .arg(
+            Arg::with_name("token")
+                .help(
+                    "Require TOKEN in the `token` query parameter, \
+                     the `Authorization: Bearer` header or a cookie on every \
+                     route.",
+                )
+                .long("token")
+                .value_name("TOKEN"),
+        )
+        .arg(
+            Arg::with_name("token-file")
+                .help("Read the token required on every route from FILE.")
+                .long("token-file")
+                .value_name("FILE")
+                .conflicts_with("token"),
+        )
+        .arg(
+            Arg::with_name("tls-cert")
+                .help(
+                    "Serve over HTTPS and WSS with the PEM certificate chain \
+                     from FILE.",
+                )
+                .long("tls-cert")
+                .value_name("FILE")
+                .requires("tls-key"),
+        )
+        .arg(
+            Arg::with_name("tls-key")
+                .help("Read the PEM private key of the certificate from FILE.")
+                .long("tls-key")
+                .value_name("FILE")
+                .requires("tls-cert"),
+        )
+        .arg(
+            Arg::with_name("listen-tcp")
+                .help("Read input from TCP connections accepted on ADDRESS.")
+                .long("listen-tcp")
+                .value_name("ADDRESS"),
+        )
+        .arg(
+            Arg::with_name("listen-udp")
+                .help("Read input from UDP datagrams received on ADDRESS.")
+                .long("listen-udp")
+                .value_name("ADDRESS"),
+        )
+        .arg(
+            Arg::with_name("input")
+                .help(
+                    "Read input from FILE. The records are stored in the \
+                     dataset NAME or in the default one. NAME cannot \
+                     contain a path separator.",
+                )
+                .long("input")
+                .short("i")
+                .value_name("[NAME=]FILE")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("follow")
+                .help("Wait for data appended to the input files.")
+                .long("follow")
+                .short("f")
+                .requires("input"),
+        )
+        .arg(
+            Arg::with_name("decompress")
+                .help("Set compression of the input.")
+                .long("decompress")
+                .value_name("COMPRESSION")
+                .possible_values(Compression::NAMES)
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("derive")
+                .help(
+                    "Add a field computed from the other fields, e.g. \
+                     'energy = 0.5 * mass * v^2' or 'dx = x - prev(x)'.",
+                )
+                .long("derive")
+                .short("d")
+                .value_name("NAME=EXPR")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|derivation| {
+                    derivation
+                        .parse::<Derivation>()
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }),
+        )
+        .arg(
+            Arg::with_name("where")
+                .help(
+                    "Store only the records for which EXPR is true, e.g. \
+                     'x > 0 && y != prev(y)'.",
+                )
+                .long("where")
+                .short("w")
+                .value_name("EXPR")
+                .validator(|expr| {
+                    expr.parse::<Expr>().map(|_| ()).map_err(|e| e.to_string())
+                }),
+        )
+        .arg(
+            Arg::with_name("fields")
+                .help("Store only the fields.")
+                .long("fields")
+                .value_name("FIELDS")
+                .multiple(true)
+                .require_delimiter(true),
+        )
+        .arg(
+            Arg::with_name("rename")
+                .help("Store the field OLD as NEW.")
+                .long("rename")
+                .value_name("OLD=NEW")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|rename| {
+                    rename
+                        .parse::<Rename>()
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }),
+        )
+        .arg(
+            Arg::with_name("rolling")
+                .help(
+                    "Add a field aggregating another one over the last N \
+                     records or the last DURATION, e.g. \
+                     'loss_avg = mean(loss, 100)' or 'temp_max = max(temp, 5s)'.",
+                )
+                .long("rolling")
+                .value_name("NAME=AGGREGATE(FIELD,WINDOW)")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|rolling| {
+                    rolling
+                        .parse::<Rolling>()
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }),
+        )
+        .arg(
+            Arg::with_name("time-field")
+                .help("Set the field holding the time of the record.")
+                .long("time-field")
+                .value_name("FIELD"),
+        )
+        .arg(
+            Arg::with_name("time-unit")
+                .help("Set the unit of the time field.")
+                .long("time-unit")
+                .value_name("UNIT")
+                .possible_values(TimeUnit::NAMES)
+                .default_value("s"),
+        )
+        .arg(
+            Arg::with_name("resample")
+                .help(
+                    "Store the records aggregated over INTERVAL of the time \
+                     field in another dataset, e.g. 100ms.",
+                )
+                .long("resample")
+                .value_name("INTERVAL")
+                .requires("time-field")
+                .validator(|interval| {
+                    parse_duration(&interval)
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }),
+        )
+        .arg(
+            Arg::with_name("resample-aggregate")
+                .help("Set how the resampled records are aggregated.")
+                .long("resample-aggregate")
+                .value_name("AGGREGATE")
+                .possible_values(Aggregate::NAMES)
+                .default_value("mean"),
+        )
+        .arg(
+            Arg::with_name("tui")
+                .help("Draw a live chart of the records in the terminal.")
+                .long("tui"),
+        )
+        .arg(
+            Arg::with_name("tui-fields")
+                .help("Set the fields drawn in the terminal.")
+                .long("tui-fields")
+                .value_name("FIELDS")
+                .multiple(true)
+                .require_delimiter(true)
+                .requires("tui"),
+        )
+        .arg(
+            Arg::with_name("no-http")
+                .help("Do not start the HTTP server.")
+                .long("no-http"),
+        )
+        .arg(
+            Arg::with_name("exit-on-eof")
+                .help(
+                    "Stop once all the inputs have ended instead of serving \
+                     the datasets.",
+                )
+                .long("exit-on-eof"),
+        )
+        .arg(
+            Arg::with_name("render-on-exit")
+                .help(
+                    "Stop once all the inputs have ended and render the \
+                     dataset to FILE. The image is PNG if FILE ends with \
+                     .png, SVG otherwise. Implies --exit-on-eof.",
+                )
+                .long("render-on-exit")
+                .value_name("FILE"),
         )
         .subcommand(
             SubCommand::with_name("json")
@@ -180,17 +641,326 @@ fn main() {
                         .short("d"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("msgpack")
+                .about("Parse incoming data as MessagePack.")
+                .help_message("Print help info.")
+                .version_message("Print version info.")
+                .arg(
+                    Arg::with_name("length-delimited")
+                        .help(
+                            "Expect each record to be preceded by its length.",
+                        )
+                        .long("length-delimited")
+                        .short("l"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cbor")
+                .about("Parse incoming data as CBOR.")
+                .help_message("Print help info.")
+                .version_message("Print version info.")
+                .arg(
+                    Arg::with_name("length-delimited")
+                        .help(
+                            "Expect each record to be preceded by its length.",
+                        )
+                        .long("length-delimited")
+                        .short("l"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("binary")
+                .about("Parse incoming data as fixed-width binary records.")
+                .help_message("Print help info.")
+                .version_message("Print version info.")
+                .arg(
+                    Arg::with_name("layout")
+                        .help("Set the fields, e.g. t:f64,x:f32,n:i32.")
+                        .long("layout")
+                        .short("l")
+                        .value_name("LAYOUT")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("endian")
+                        .help("Set the byte order.")
+                        .long("endian")
+                        .short("e")
+                        .value_name("ENDIAN")
+                        .possible_values(Endian::NAMES)
+                        .default_value("little"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("arrow")
+                .about("Parse incoming data as an Arrow IPC stream.")
+                .help_message("Print help info.")
+                .version_message("Print version info."),
+        )
+        .subcommand(
+            SubCommand::with_name("regex")
+                .about("Parse incoming lines with a regex.")
+                .help_message("Print help info.")
+                .version_message("Print version info.")
+                .arg(
+                    Arg::with_name("pattern")
+                        .help(
+                            "Set the pattern. Names of its capture groups \
+                             become the fields.",
+                        )
+                        .value_name("PATTERN")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("skip-unmatched")
+                        .help("Skip lines which do not match the pattern.")
+                        .long("skip-unmatched")
+                        .short("k"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("logfmt")
+                .about("Parse incoming data as logfmt (key=value pairs).")
+                .help_message("Print help info.")
+                .version_message("Print version info.")
+                .arg(
+                    Arg::with_name("strict")
+                        .help(
+                            "Treat lines with non-numeric values as invalid \
+                             instead of skipping the values.",
+                        )
+                        .long("strict"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("influx")
+                .about("Parse incoming data as InfluxDB line protocol.")
+                .help_message("Print help info.")
+                .version_message("Print version info.")
+                .arg(
+                    Arg::with_name("ignore-tags")
+                        .help(
+                            "Choose the dataset by the measurement only \
+                             instead of the measurement and the tags.",
+                        )
+                        .long("ignore-tags"),
+                )
+                .arg(
+                    Arg::with_name("precision")
+                        .help("Set the precision of generated timestamps.")
+                        .long("precision")
+                        .value_name("PRECISION")
+                        .possible_values(Precision::NAMES)
+                        .default_value("ns"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("prometheus")
+                .about("Parse incoming data as Prometheus text format.")
+                .help_message("Print help info.")
+                .version_message("Print version info.")
+                .arg(
+                    Arg::with_name("scrape")
+                        .help("Read input by scraping metrics from URL.")
+                        .long("scrape")
+                        .value_name("URL"),
+                )
+                .arg(
+                    Arg::with_name("every")
+                        .help("Set the interval between scrapes (default 1s).")
+                        .long("every")
+                        .value_name("INTERVAL")
+                        .requires("scrape"),
+                )
+                .arg(
+                    Arg::with_name("series")
+                        .help(
+                            "Store only the series, chosen by the metric \
+                             name or by the name with labels.",
+                        )
+                        .long("series")
+                        .value_name("SERIES")
+                        .multiple(true)
+                        .number_of_values(1),
+                ),
+        )
         .get_matches();
     let ip_addr = matches
         .value_of("ip-address")
         .unwrap_or("127.0.0.1:8080")
         .to_string();
-    let static_path = matches.value_of("static-path").unwrap().to_string();
+    let token = match matches.value_of("token-file") {
+        Some(path) => Some(
+            std::fs::read_to_string(path)
+                .expect("cannot read the token file")
+                .trim()
+                .to_string(),
+        ),
+        None => matches.value_of("token").map(String::from),
+    };
+    let tls = match (matches.value_of("tls-cert"), matches.value_of("tls-key"))
+    {
+        (Some(cert_path), Some(key_path)) => Some(
+            load_tls_config(Path::new(cert_path), Path::new(key_path))
+                .expect("invalid TLS certificate or key"),
+        ),
+        _ => None,
+    };
+    let token = token.map(|token| {
+        TokenAuth::new(&token, tls.is_some()).expect("invalid token")
+    });
+    let http = if matches.is_present("no-http") {
+        None
+    } else {
+        Some(HttpSettings {
+            static_path: matches.value_of("static-path").map(String::from),
+            ip_addr,
+            token,
+            tls,
+        })
+    };
+    let compression =
+        Compression::from_name(matches.value_of("decompress").unwrap())
+            .unwrap();
+    let derivations = matches
+        .values_of("derive")
+        .into_iter()
+        .flatten()
+        .map(|derivation| derivation.parse().unwrap())
+        .collect::<Vec<_>>();
+    let rollings = matches
+        .values_of("rolling")
+        .into_iter()
+        .flatten()
+        .map(|rolling| rolling.parse::<Rolling>().unwrap())
+        .collect::<Vec<_>>();
+    let time_field = matches.value_of("time-field").map(|name| TimeField {
+        name: name.to_string(),
+        unit: TimeUnit::from_name(matches.value_of("time-unit").unwrap())
+            .unwrap(),
+    });
+    let has_time_window = rollings
+        .iter()
+        .any(|rolling| matches!(rolling.window, Window::Duration(_)));
+    if has_time_window && time_field.is_none() {
+        ClapError::with_description(
+            "a rolling aggregate over a duration requires --time-field",
+            ClapErrorKind::MissingRequiredArgument,
+        )
+        .exit();
+    }
+    // Draw the charts against the time field if there is one.
+    let time_field_name = time_field.as_ref().map(|field| field.name.clone());
+    let transform = Transform::new(TransformSettings {
+        derivations,
+        filter: matches.value_of("where").map(|expr| expr.parse().unwrap()),
+        fields: matches
+            .values_of("fields")
+            .map(|fields| fields.map(|field| field.to_string()).collect()),
+        renames: matches
+            .values_of("rename")
+            .into_iter()
+            .flatten()
+            .map(|rename| rename.parse().unwrap())
+            .collect(),
+        rollings,
+        time_field,
+        resampling: matches.value_of("resample").map(|interval| Resampling {
+            interval: parse_duration(interval).unwrap(),
+            aggregate: Aggregate::from_name(
+                matches.value_of("resample-aggregate").unwrap(),
+            )
+            .unwrap(),
+        }),
+    });
+
+    // Read stdin only if no other source has been chosen.
+    let mut sources = vec![];
+    if let Some(ip_addr) = matches.value_of("listen-tcp") {
+        sources.push((
+            DEFAULT_DATASET.to_string(),
+            InputSource::Tcp(ip_addr.to_string()),
+        ));
+    }
+    if let Some(ip_addr) = matches.value_of("listen-udp") {
+        sources.push((
+            DEFAULT_DATASET.to_string(),
+            InputSource::Udp(ip_addr.to_string()),
+        ));
+    }
+    for input in matches.values_of("input").into_iter().flatten() {
+        let (dataset, path) = split_input(input);
+
+        sources.push((
+            dataset.to_string(),
+            InputSource::File {
+                path: path.into(),
+                follow: matches.is_present("follow"),
+            },
+        ));
+    }
+    if let Some(matches) = matches.subcommand_matches("prometheus") {
+        if let Some(url) = matches.value_of("scrape") {
+            let interval =
+                parse_duration(matches.value_of("every").unwrap_or("1s"))
+                    .expect("invalid scrape interval");
+
+            sources.push((
+                DEFAULT_DATASET.to_string(),
+                InputSource::Scrape {
+                    url: url.to_string(),
+                    interval,
+                },
+            ));
+        }
+    }
+    if sources.is_empty() {
+        sources.push((DEFAULT_DATASET.to_string(), InputSource::Stdin));
+    }
+
+    // Draw the dataset of the first source.
+    let render_on_exit =
+        matches.value_of("render-on-exit").map(|path| RenderTarget {
+            path: path.into(),
+            dataset: sources[0].0.clone(),
+            settings: RenderSettings {
+                x: time_field_name.clone(),
+                ..RenderSettings::default()
+            },
+        });
+    // Batch runs rendering an image stop once the inputs have ended.
+    let exit_on_eof =
+        matches.is_present("exit-on-eof") || render_on_exit.is_some();
+    let terminal = if matches.is_present("tui") {
+        Some(TerminalView {
+            dataset: sources[0].0.clone(),
+            settings: RenderSettings {
+                x: time_field_name,
+                y: matches
+                    .values_of("tui-fields")
+                    .map(|fields| fields.map(String::from).collect()),
+                ..RenderSettings::default()
+            },
+        })
+    } else {
+        None
+    };
 
     if let Some(_matches) = matches.subcommand_matches("json") {
         let parser = JsonParser::new();
 
-        run_app(parser, (), static_path, ip_addr);
+        run_app(
+            parser,
+            sources,
+            (),
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
     } else if let Some(matches) = matches.subcommand_matches("csv") {
         // create a parser.
         let headers = matches
@@ -211,6 +981,240 @@ fn main() {
         });
         let reader_settings = (headers, delim);
 
-        run_app(parser, reader_settings, static_path, ip_addr);
+        run_app(
+            parser,
+            sources,
+            reader_settings,
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    } else if let Some(matches) = matches.subcommand_matches("msgpack") {
+        let parser = MsgpackParser::new();
+        let framing = if matches.is_present("length-delimited") {
+            Framing::LengthDelimited
+        } else {
+            Framing::SelfDelimiting
+        };
+
+        run_app(
+            parser,
+            sources,
+            framing,
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    } else if let Some(matches) = matches.subcommand_matches("cbor") {
+        let parser = CborParser::new();
+        let framing = if matches.is_present("length-delimited") {
+            Framing::LengthDelimited
+        } else {
+            Framing::SelfDelimiting
+        };
+
+        run_app(
+            parser,
+            sources,
+            framing,
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    } else if let Some(matches) = matches.subcommand_matches("binary") {
+        let endian =
+            Endian::from_name(matches.value_of("endian").unwrap()).unwrap();
+        let parser = BinaryParser::from_layout(
+            matches.value_of("layout").unwrap(),
+            endian,
+        )
+        .expect("invalid binary layout");
+        let record_size = parser.record_size();
+
+        run_app(
+            parser,
+            sources,
+            record_size,
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    } else if let Some(_matches) = matches.subcommand_matches("arrow") {
+        let parser = ArrowParser::new();
+
+        run_app(
+            parser,
+            sources,
+            (),
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    } else if let Some(matches) = matches.subcommand_matches("regex") {
+        let regex = Regex::new(matches.value_of("pattern").unwrap())
+            .expect("invalid regex");
+        let parser =
+            RegexParser::new(regex, matches.is_present("skip-unmatched"))
+                .expect("regex has no named capture groups");
+
+        run_app(
+            parser,
+            sources,
+            (),
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    } else if let Some(matches) = matches.subcommand_matches("logfmt") {
+        let parser = LogfmtParser::new(matches.is_present("strict"));
+
+        run_app(
+            parser,
+            sources,
+            (),
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    } else if let Some(matches) = matches.subcommand_matches("influx") {
+        let precision =
+            Precision::from_name(matches.value_of("precision").unwrap())
+                .unwrap();
+        let parser =
+            InfluxParser::new(matches.is_present("ignore-tags"), precision);
+
+        run_app(
+            parser,
+            sources,
+            (),
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    } else if let Some(matches) = matches.subcommand_matches("prometheus") {
+        let series = matches
+            .values_of("series")
+            .into_iter()
+            .flatten()
+            .map(|series| series.to_string())
+            .collect::<Vec<_>>();
+        let parser = PrometheusParser::new(series);
+
+        run_app(
+            parser,
+            sources,
+            (),
+            transform,
+            compression,
+            http,
+            terminal,
+            render_on_exit,
+            exit_on_eof,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::arrow::{array::Int64Array, ipc::reader::StreamReader};
+    use ::pretty_assertions::assert_eq;
+    use ::serde_json::json;
+
+    use std::io;
+
+    use super::*;
+    use crate::{parse::framed::MAX_FRAME_SIZE, server::ExportArrow};
+
+    #[test]
+    fn test_split_input() {
+        assert_eq!(split_input("run1=a.csv"), ("run1", "a.csv"));
+        assert_eq!(split_input("a.csv"), (DEFAULT_DATASET, "a.csv"));
+        assert_eq!(
+            split_input("runs/lr=0.1.csv"),
+            (DEFAULT_DATASET, "runs/lr=0.1.csv")
+        );
+        assert_eq!(
+            split_input("./lr=0.1.csv"),
+            (DEFAULT_DATASET, "./lr=0.1.csv")
+        );
+        assert_eq!(split_input("lr=0.1.csv"), ("lr", "0.1.csv"));
+        assert_eq!(split_input("=a.csv"), (DEFAULT_DATASET, "=a.csv"));
+    }
+
+    #[test]
+    fn test_feed_after_oversized_frame() {
+        let mut sys = System::new("test");
+        let (stop_tx, _) = channel();
+        let addr = Server::new(
+            stop_tx,
+            MsgpackParser::new(),
+            vec![],
+            Transform::default(),
+            None,
+            false,
+        )
+        .start();
+
+        let mut contents = vec![];
+        for a in &[1, 2] {
+            let record = ::rmp_serde::to_vec(&json!({ "a": a })).unwrap();
+            contents.extend(&(MAX_FRAME_SIZE as u32 + 1).to_be_bytes());
+            contents.extend(vec![0; MAX_FRAME_SIZE + 1]);
+            contents.extend(&(record.len() as u32).to_be_bytes());
+            contents.extend(&record);
+        }
+
+        feed(
+            DEFAULT_DATASET,
+            Box::new(io::Cursor::new(contents)),
+            Framing::LengthDelimited,
+            Compression::None,
+            &addr,
+        );
+
+        let bytes = sys
+            .block_on(addr.send(ExportArrow {
+                dataset: DEFAULT_DATASET.to_string(),
+            }))
+            .unwrap()
+            .unwrap();
+        let batch = StreamReader::try_new(&bytes[..], None)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let a = batch
+            .column_by_name("a")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+
+        // The oversized frames are skipped, the records after them are read.
+        assert_eq!(a.values().to_vec(), vec![1, 2]);
     }
 }
@@ -0,0 +1,336 @@
+//! Arrow IPC stream parsing.
+
+use ::arrow::{
+    array::{Array, ArrayRef, BooleanArray, Float64Array, Int64Array},
+    compute::{and, cast, filter, is_not_null},
+    datatypes::DataType,
+    ipc::reader::StreamReader,
+    record_batch::RecordBatch,
+};
+
+use std::io::{self, BufReader};
+
+use super::{record::Record, Columns, ParseError, Parser, ReadError};
+use crate::storage::NumberVec;
+
+/// Numeric columns of a record batch.
+///
+/// Integer, boolean and timestamp columns are cast to `Int64`, floating
+/// point columns to `Float64`. Other columns are skipped. Values which do not
+/// fit in `Int64`, e.g. large `UInt64` ones, become nulls.
+pub struct ArrowBatch {
+    columns: Vec<(String, ArrayRef)>,
+    rows: usize,
+}
+
+impl ArrowBatch {
+    fn from_record_batch(batch: &RecordBatch) -> Result<Self, ReadError> {
+        let columns = batch
+            .schema_ref()
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .filter_map(|(field, column)| {
+                let data_type = match field.data_type() {
+                    data_type if data_type.is_floating() => DataType::Float64,
+                    data_type if data_type.is_integer() => DataType::Int64,
+                    DataType::Boolean | DataType::Timestamp(_, _) =>
+                        DataType::Int64,
+                    _ => return None,
+                };
+
+                Some(
+                    cast(column, &data_type)
+                        .map(|column| (field.name().clone(), column))
+                        .map_err(|_| ReadError::Invalid),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ArrowBatch {
+            columns,
+            rows: batch.num_rows(),
+        })
+    }
+
+    /// Convert the batch into columns, leaving out the rows with a null
+    /// value in any of them.
+    fn to_columns(&self) -> Result<Columns, ParseError> {
+        let mut valid = BooleanArray::from(vec![true; self.rows]);
+        for (_, column) in &self.columns {
+            if column.null_count() > 0 {
+                valid = is_not_null(column)
+                    .and_then(|not_null| and(&valid, &not_null))
+                    .map_err(|_| ParseError {})?;
+            }
+        }
+        let len = valid.true_count();
+
+        let columns = self
+            .columns
+            .iter()
+            .map(|(field, column)| {
+                let column = if len < self.rows {
+                    filter(column, &valid).map_err(|_| ParseError {})?
+                } else {
+                    column.clone()
+                };
+
+                let any = column.as_any();
+                let column = if let Some(column) =
+                    any.downcast_ref::<Float64Array>()
+                {
+                    NumberVec::Float(column.values().to_vec())
+                } else if let Some(column) = any.downcast_ref::<Int64Array>() {
+                    NumberVec::Int(column.values().to_vec())
+                } else {
+                    return Err(ParseError {});
+                };
+
+                Ok((field.clone(), column))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Columns {
+            columns,
+            len,
+            rejected: self.rows - len,
+        })
+    }
+}
+
+/// Iterator yielding the record batches from an IPC stream.
+pub struct ArrowReader<R>
+where
+    R: io::Read,
+{
+    /// `None` if the stream does not start with a valid schema.
+    reader: Option<StreamReader<BufReader<R>>>,
+    /// Whether the invalid schema still has to be reported.
+    invalid: bool,
+}
+
+impl<R> ArrowReader<R>
+where
+    R: io::Read,
+{
+    /// Wrap the reader, reading the schema right away.
+    pub fn new(reader: R) -> Self {
+        let reader = StreamReader::try_new_buffered(reader, None).ok();
+
+        Self {
+            invalid: reader.is_none(),
+            reader,
+        }
+    }
+}
+
+impl<R> Iterator for ArrowReader<R>
+where
+    R: io::Read,
+{
+    type Item = Result<ArrowBatch, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = match self.reader.as_mut() {
+            Some(reader) => reader.next()?,
+            None if self.invalid => {
+                self.invalid = false;
+
+                return Some(Err(ReadError::Io));
+            }
+            None => return None,
+        };
+
+        Some(
+            batch
+                .map_err(|_| ReadError::Io)
+                .and_then(|batch| ArrowBatch::from_record_batch(&batch)),
+        )
+    }
+}
+
+/// Arrow IPC stream parser.
+///
+/// Every input is a whole record batch, appended to the storage column by
+/// column.
+pub struct ArrowParser;
+
+impl ArrowParser {
+    /// Create an Arrow IPC stream parser.
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<R> Parser<R> for ArrowParser
+where
+    R: io::Read,
+{
+    type Input = ArrowBatch;
+
+    type Settings = ();
+
+    const LINE_BASED: bool = false;
+
+    type Reader = ArrowReader<R>;
+
+    fn wrap_reader(reader: R, _settings: Self::Settings) -> Self::Reader {
+        ArrowReader::new(reader)
+    }
+
+    /// A record batch does not make a single record, see
+    /// [`parse_columns`][Parser::parse_columns].
+    fn parse<'a>(
+        &'a self,
+        _input: &'a Self::Input,
+    ) -> Result<Record<'a>, ParseError> {
+        Err(ParseError {})
+    }
+
+    fn parse_columns(
+        &self,
+        input: &Self::Input,
+    ) -> Option<Result<Columns, ParseError>> {
+        Some(input.to_columns())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::arrow::{
+        array::{Int32Array, UInt64Array},
+        datatypes::{Field, Schema},
+        ipc::writer::StreamWriter,
+    };
+    use ::pretty_assertions::assert_eq;
+
+    use std::sync::Arc;
+
+    use super::*;
+
+    use crate::{
+        export::export_arrow,
+        storage::{Number, Storage},
+    };
+
+    fn write_stream(batch: &RecordBatch) -> Vec<u8> {
+        let mut writer =
+            StreamWriter::try_new(vec![], &batch.schema()).unwrap();
+        writer.write(batch).unwrap();
+        writer.finish().unwrap();
+
+        writer.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_parse_arrow() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Float64, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 11])),
+                Arc::new(Float64Array::from(vec![2.0, 12.0])),
+            ],
+        )
+        .unwrap();
+        let contents = write_stream(&batch);
+
+        let parser = ArrowParser::new();
+        let mut reader =
+            <ArrowParser as Parser<&[u8]>>::wrap_reader(&contents[..], ());
+
+        let input = reader.next().unwrap().unwrap();
+        let columns = Parser::<&[u8]>::parse_columns(&parser, &input)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(columns.len, 2);
+        assert_eq!(columns.rejected, 0);
+        assert_eq!(columns.columns["a"].int().unwrap(), &vec![1, 11]);
+        assert_eq!(columns.columns["b"].float().unwrap(), &vec![2.0, 12.0]);
+
+        let records = columns.records().collect::<Vec<_>>();
+
+        assert_eq!(records[1]["a"], Number::Int(11));
+        assert_eq!(records[1]["b"], Number::Float(12.0));
+
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_reject_invalid_rows() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::UInt64, false),
+            Field::new("b", DataType::Float64, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(UInt64Array::from(vec![1, u64::MAX, 3, 4])),
+                Arc::new(Float64Array::from(vec![
+                    Some(2.0),
+                    Some(12.0),
+                    None,
+                    Some(42.0),
+                ])),
+            ],
+        )
+        .unwrap();
+        let contents = write_stream(&batch);
+
+        let parser = ArrowParser::new();
+        let mut reader =
+            <ArrowParser as Parser<&[u8]>>::wrap_reader(&contents[..], ());
+
+        let input = reader.next().unwrap().unwrap();
+        let columns = Parser::<&[u8]>::parse_columns(&parser, &input)
+            .unwrap()
+            .unwrap();
+
+        // The value overflowing `i64` and the null leave out their rows.
+        assert_eq!(columns.len, 2);
+        assert_eq!(columns.rejected, 2);
+        assert_eq!(columns.columns["a"].int().unwrap(), &vec![1, 4]);
+        assert_eq!(columns.columns["b"].float().unwrap(), &vec![2.0, 42.0]);
+    }
+
+    #[test]
+    fn test_export_arrow() {
+        let parser = ArrowParser::new();
+        let mut storage = Storage::new();
+
+        for (a, b) in &[(1, 2.0), (11, 12.0)] {
+            let mut record = Record(Default::default());
+            record.0.insert("a".into(), Number::Int(*a));
+            record.0.insert("b".into(), Number::Float(*b));
+
+            storage.push_record(&record).unwrap();
+        }
+
+        let contents = export_arrow(&storage).unwrap();
+        let mut reader =
+            <ArrowParser as Parser<&[u8]>>::wrap_reader(&contents[..], ());
+
+        let input = reader.next().unwrap().unwrap();
+        let columns = Parser::<&[u8]>::parse_columns(&parser, &input)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(columns.columns["a"].int().unwrap(), &vec![1, 11]);
+        assert_eq!(columns.columns["b"].float().unwrap(), &vec![2.0, 12.0]);
+        assert_eq!(reader.count(), 0);
+    }
+
+    #[test]
+    fn test_read_invalid_arrow_stream() {
+        let mut reader =
+            <ArrowParser as Parser<&[u8]>>::wrap_reader(&b"invalid"[..], ());
+
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,338 @@
+//! Raw binary parsing.
+//!
+//! Records are sequences of fixed-width numbers described by a layout, e.g.
+//! `t:f64,x:f32,n:i32`. They are decoded directly, without serde.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    io::{self, Read},
+    mem,
+};
+
+use super::{record::Record, ParseError, Parser, ReadError};
+use crate::storage::Number;
+
+/// Byte order of the numbers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endian {
+    /// Little-endian.
+    Little,
+    /// Big-endian.
+    Big,
+}
+
+impl Endian {
+    /// Names of the variants accepted by [`Endian::from_name`].
+    pub const NAMES: &'static [&'static str] = &["little", "big"];
+
+    /// Get the variant by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "little" => Some(Endian::Little),
+            "big" => Some(Endian::Big),
+            _ => None,
+        }
+    }
+}
+
+/// Type of a field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+    /// `i8`.
+    I8,
+    /// `i16`.
+    I16,
+    /// `i32`.
+    I32,
+    /// `i64`.
+    I64,
+    /// `u8`.
+    U8,
+    /// `u16`.
+    U16,
+    /// `u32`.
+    U32,
+    /// `u64`.
+    U64,
+    /// `f32`.
+    F32,
+    /// `f64`.
+    F64,
+}
+
+macro_rules! decode {
+    ($ty:ty, $bytes:expr, $endian:expr) => {{
+        let mut buf = [0; mem::size_of::<$ty>()];
+        buf.copy_from_slice($bytes);
+
+        match $endian {
+            Endian::Little => <$ty>::from_le_bytes(buf),
+            Endian::Big => <$ty>::from_be_bytes(buf),
+        }
+    }};
+}
+
+impl FieldType {
+    /// Get the type by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "i8" => Some(FieldType::I8),
+            "i16" => Some(FieldType::I16),
+            "i32" => Some(FieldType::I32),
+            "i64" => Some(FieldType::I64),
+            "u8" => Some(FieldType::U8),
+            "u16" => Some(FieldType::U16),
+            "u32" => Some(FieldType::U32),
+            "u64" => Some(FieldType::U64),
+            "f32" => Some(FieldType::F32),
+            "f64" => Some(FieldType::F64),
+            _ => None,
+        }
+    }
+
+    /// Get the size of the field in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            FieldType::I8 | FieldType::U8 => 1,
+            FieldType::I16 | FieldType::U16 => 2,
+            FieldType::I32 | FieldType::U32 | FieldType::F32 => 4,
+            FieldType::I64 | FieldType::U64 | FieldType::F64 => 8,
+        }
+    }
+
+    /// Decode the number. `bytes` must be exactly [`FieldType::size`] long.
+    ///
+    /// Returns `None` for `u64` values which do not fit in an `i64`. Integers
+    /// are stored as `i64`, and storing some values of the field as floats
+    /// would make the types of the records mismatch.
+    fn decode(self, bytes: &[u8], endian: Endian) -> Option<Number> {
+        let number = match self {
+            FieldType::I8 => Number::Int(i64::from(decode!(i8, bytes, endian))),
+            FieldType::I16 =>
+                Number::Int(i64::from(decode!(i16, bytes, endian))),
+            FieldType::I32 =>
+                Number::Int(i64::from(decode!(i32, bytes, endian))),
+            FieldType::I64 => Number::Int(decode!(i64, bytes, endian)),
+            FieldType::U8 => Number::Int(i64::from(decode!(u8, bytes, endian))),
+            FieldType::U16 =>
+                Number::Int(i64::from(decode!(u16, bytes, endian))),
+            FieldType::U32 =>
+                Number::Int(i64::from(decode!(u32, bytes, endian))),
+            FieldType::U64 =>
+                Number::Int(i64::try_from(decode!(u64, bytes, endian)).ok()?),
+            FieldType::F32 =>
+                Number::Float(f64::from(decode!(f32, bytes, endian))),
+            FieldType::F64 => Number::Float(decode!(f64, bytes, endian)),
+        };
+
+        Some(number)
+    }
+}
+
+/// Binary parser.
+#[derive(Debug)]
+pub struct BinaryParser {
+    fields: Vec<(String, FieldType)>,
+    endian: Endian,
+}
+
+impl BinaryParser {
+    /// Create a binary parser from a layout, i.e. a comma-separated list of
+    /// `name:type` pairs. Returns `None` if the layout is invalid or if a
+    /// name is repeated.
+    pub fn from_layout(layout: &str, endian: Endian) -> Option<Self> {
+        let fields = layout
+            .split(',')
+            .map(|field| {
+                let mut parts = field.splitn(2, ':');
+                let name = parts.next().filter(|name| !name.is_empty())?;
+                let field_type = FieldType::from_name(parts.next()?)?;
+
+                Some((name.to_string(), field_type))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let names = fields.iter().map(|(name, _)| name).collect::<HashSet<_>>();
+        if names.len() != fields.len() {
+            return None;
+        }
+
+        Some(Self { fields, endian })
+    }
+
+    /// Get the size of a record in bytes.
+    pub fn record_size(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|(_, field_type)| field_type.size())
+            .sum()
+    }
+}
+
+/// Iterator yielding records of a fixed size.
+pub struct BinaryReader<R>
+where
+    R: io::Read,
+{
+    reader: R,
+    record_size: usize,
+}
+
+impl<R> BinaryReader<R>
+where
+    R: io::Read,
+{
+    /// Wrap the reader, reading records of `record_size` bytes.
+    pub fn new(reader: R, record_size: usize) -> Self {
+        Self {
+            reader,
+            record_size,
+        }
+    }
+}
+
+impl<R> Iterator for BinaryReader<R>
+where
+    R: io::Read,
+{
+    type Item = Result<Vec<u8>, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = Vec::with_capacity(self.record_size);
+
+        match (&mut self.reader)
+            .take(self.record_size as u64)
+            .read_to_end(&mut record)
+        {
+            Ok(0) => None,
+            Ok(len) if len == self.record_size => Some(Ok(record)),
+            // A truncated record at the end of the input.
+            Ok(_) => Some(Err(ReadError::Invalid)),
+            Err(_) => Some(Err(ReadError::Io)),
+        }
+    }
+}
+
+impl<R> Parser<R> for BinaryParser
+where
+    R: io::Read,
+{
+    type Input = Vec<u8>;
+
+    /// Size of a record in bytes.
+    type Settings = usize;
+
+    const LINE_BASED: bool = false;
+
+    type Reader = BinaryReader<R>;
+
+    fn wrap_reader(reader: R, settings: Self::Settings) -> Self::Reader {
+        BinaryReader::new(reader, settings)
+    }
+
+    fn parse<'a>(
+        &'a self,
+        input: &'a Self::Input,
+    ) -> Result<Record<'a>, ParseError> {
+        if input.len() != self.record_size() {
+            return Err(ParseError {});
+        }
+
+        let mut offset = 0;
+        let record = self
+            .fields
+            .iter()
+            .map(|(name, field_type)| {
+                let bytes = &input[offset..offset + field_type.size()];
+                offset += field_type.size();
+
+                let number = field_type
+                    .decode(bytes, self.endian)
+                    .ok_or(ParseError {})?;
+
+                Ok((Cow::Borrowed(name.as_str()), number))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(Record(record))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_binary() {
+        let parser =
+            BinaryParser::from_layout("t:f64,x:f32,n:i32", Endian::Little)
+                .unwrap();
+
+        let mut input = vec![];
+        input.extend(&1.5f64.to_le_bytes());
+        input.extend(&2.5f32.to_le_bytes());
+        input.extend(&(-3i32).to_le_bytes());
+
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert_eq!(record["t"], Number::Float(1.5));
+        assert_eq!(record["x"], Number::Float(2.5));
+        assert_eq!(record["n"], Number::Int(-3));
+    }
+
+    #[test]
+    fn test_parse_big_endian_binary() {
+        let parser =
+            BinaryParser::from_layout("a:u16,b:i64", Endian::Big).unwrap();
+
+        let mut input = vec![];
+        input.extend(&7u16.to_be_bytes());
+        input.extend(&(-8i64).to_be_bytes());
+
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert_eq!(record["a"], Number::Int(7));
+        assert_eq!(record["b"], Number::Int(-8));
+    }
+
+    #[test]
+    fn test_parse_u64_binary() {
+        let parser =
+            BinaryParser::from_layout("a:u64", Endian::Little).unwrap();
+
+        let input = (i64::MAX as u64).to_le_bytes().to_vec();
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert_eq!(record["a"], Number::Int(i64::MAX));
+
+        let input = (i64::MAX as u64 + 1).to_le_bytes().to_vec();
+
+        assert!(Parser::<&[u8]>::parse(&parser, &input).is_err());
+    }
+
+    #[test]
+    fn test_read_fixed_size_records() {
+        let contents = [1u8, 2, 3, 4, 5];
+
+        let mut reader =
+            <BinaryParser as Parser<&[u8]>>::wrap_reader(&contents[..], 2);
+
+        assert_eq!(reader.next().unwrap().unwrap(), vec![1, 2]);
+        assert_eq!(reader.next().unwrap().unwrap(), vec![3, 4]);
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_invalid_layout() {
+        assert!(BinaryParser::from_layout("a:f16", Endian::Little).is_none());
+        assert!(BinaryParser::from_layout("a", Endian::Little).is_none());
+        assert!(BinaryParser::from_layout(":i8", Endian::Little).is_none());
+        assert!(
+            BinaryParser::from_layout("t:f64,t:f32", Endian::Little).is_none()
+        );
+    }
+}
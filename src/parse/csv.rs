@@ -50,7 +50,12 @@ where
     type Item = Result<CsvStringRecord, ReadError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|record| record.map_err(|_| ReadError {}))
+        self.0.next().map(|record| {
+            record.map_err(|e| match e.kind() {
+                csv::ErrorKind::Io(_) => ReadError::Io,
+                _ => ReadError::Invalid,
+            })
+        })
     }
 }
 
@@ -84,7 +89,7 @@ where
 mod test {
     use ::pretty_assertions::assert_eq;
 
-    use std::io::BufReader;
+    use std::io::{BufReader, Read};
 
     use super::*;
 
@@ -94,7 +99,7 @@ mod test {
         reader: R,
         mb_delimiter: Option<u8>,
         ignore_first_line: bool,
-    ) -> CsvReader<R>
+    ) -> csv::Reader<R>
     where
         R: Read,
     {
@@ -119,13 +124,13 @@ mod test {
         let parser = CsvParser::new(headers);
 
         let input = records.next().unwrap().unwrap();
-        let record_1 = parser.parse(&input).unwrap();
+        let record_1 = Parser::<&[u8]>::parse(&parser, &input).unwrap();
 
         assert_eq!(record_1["a"], Number::Int(1));
         assert_eq!(record_1["b"], Number::Int(2));
 
         let input = records.next().unwrap().unwrap();
-        let record_2 = parser.parse(&input).unwrap();
+        let record_2 = Parser::<&[u8]>::parse(&parser, &input).unwrap();
 
         assert_eq!(record_2["a"], Number::Int(11));
         assert_eq!(record_2["b"], Number::Int(12));
@@ -144,13 +149,13 @@ mod test {
         let parser = CsvParser::new(headers);
 
         let input = records.next().unwrap().unwrap();
-        let record_1 = parser.parse(&input).unwrap();
+        let record_1 = Parser::<&[u8]>::parse(&parser, &input).unwrap();
 
         assert_eq!(record_1["a"], Number::Float(1.0));
         assert_eq!(record_1["b"], Number::Float(2.0));
 
         let input = records.next().unwrap().unwrap();
-        let record_2 = parser.parse(&input).unwrap();
+        let record_2 = Parser::<&[u8]>::parse(&parser, &input).unwrap();
 
         assert_eq!(record_2["a"], Number::Float(11.0));
         assert_eq!(record_2["b"], Number::Float(12.0));
@@ -169,7 +174,7 @@ mod test {
         let parser = CsvParser::new(headers);
 
         let input = records.next().unwrap().unwrap();
-        let record_1 = parser.parse(&input).unwrap();
+        let record_1 = Parser::<&[u8]>::parse(&parser, &input).unwrap();
 
         assert_eq!(record_1["a"], Number::Int(1));
         assert_eq!(record_1["b"], Number::Int(2));
@@ -189,6 +194,6 @@ mod test {
         let parser = CsvParser::new(headers);
 
         let input = records.next().unwrap().unwrap();
-        let _record = parser.parse(&input).unwrap();
+        let _record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
     }
 }
@@ -0,0 +1,126 @@
+//! Framing of binary records.
+
+use std::io::{self, BufRead, BufReader, Read};
+
+use super::ReadError;
+
+/// Maximal length of a length-delimited record. Longer records are skipped
+/// and reported as read errors, so that a corrupted length does not make the
+/// reader allocate gigabytes.
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// How consecutive binary records are separated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+    /// Each record is preceded by its length, a big-endian `u32`.
+    LengthDelimited,
+    /// Records follow each other directly. Their ends are found by
+    /// decoding them.
+    SelfDelimiting,
+}
+
+/// A function reading a single self-delimiting value.
+pub type SkipValue = fn(&mut dyn Read) -> Result<(), ReadError>;
+
+/// A reader remembering the bytes read through it.
+struct Recorder<'a, R>
+where
+    R: Read,
+{
+    reader: &'a mut R,
+    record: Vec<u8>,
+}
+
+impl<'a, R> Read for Recorder<'a, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        self.record.extend_from_slice(&buf[..len]);
+
+        Ok(len)
+    }
+}
+
+/// Iterator yielding raw bytes of consecutive records.
+pub struct FramedReader<R>
+where
+    R: io::Read,
+{
+    reader: BufReader<R>,
+    framing: Framing,
+    skip_value: SkipValue,
+}
+
+impl<R> FramedReader<R>
+where
+    R: io::Read,
+{
+    /// Create a reader. `skip_value` is only used to find the ends of
+    /// self-delimiting records.
+    pub fn new(reader: R, framing: Framing, skip_value: SkipValue) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            framing,
+            skip_value,
+        }
+    }
+
+    fn read_length_delimited(&mut self) -> Result<Vec<u8>, ReadError> {
+        let mut len = [0; 4];
+        self.reader
+            .read_exact(&mut len)
+            .map_err(|_| ReadError::Io)?;
+
+        let len = u32::from_be_bytes(len) as usize;
+
+        if len > MAX_FRAME_SIZE {
+            // Skip the record to keep reading the next ones.
+            io::copy(
+                &mut self.reader.by_ref().take(len as u64),
+                &mut io::sink(),
+            )
+            .map_err(|_| ReadError::Io)?;
+
+            return Err(ReadError::Invalid);
+        }
+
+        let mut record = vec![0; len];
+        self.reader
+            .read_exact(&mut record)
+            .map_err(|_| ReadError::Io)?;
+
+        Ok(record)
+    }
+
+    fn read_self_delimiting(&mut self) -> Result<Vec<u8>, ReadError> {
+        let mut recorder = Recorder {
+            reader: &mut self.reader,
+            record: vec![],
+        };
+        (self.skip_value)(&mut recorder)?;
+
+        Ok(recorder.record)
+    }
+}
+
+impl<R> Iterator for FramedReader<R>
+where
+    R: io::Read,
+{
+    type Item = Result<Vec<u8>, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader.fill_buf() {
+            Ok([]) => return None,
+            Err(_) => return Some(Err(ReadError::Io)),
+            _ => {}
+        }
+
+        match self.framing {
+            Framing::LengthDelimited => Some(self.read_length_delimited()),
+            Framing::SelfDelimiting => Some(self.read_self_delimiting()),
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! InfluxDB line protocol parsing.
+//!
+//! Each line has the form `measurement,tag=value field=1.2,other=3i
+//! 1690000000`. The series (the measurement and the tags) becomes the dataset,
+//! the tags become its labels, the fields become the fields of the record and
+//! the timestamp is stored in the `time` field.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    convert::TryFrom,
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    lines::LineReader,
+    record::Record,
+    LabelPairs,
+    ParseError,
+    Parser,
+    ReadError,
+};
+use crate::storage::Number;
+
+/// The name of the field the timestamp is stored in.
+pub const TIME_FIELD: &str = "time";
+
+/// Precision of the timestamps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precision {
+    /// Nanoseconds.
+    Nanoseconds,
+    /// Microseconds.
+    Microseconds,
+    /// Milliseconds.
+    Milliseconds,
+    /// Seconds.
+    Seconds,
+}
+
+impl Precision {
+    /// Names of the variants accepted by [`Precision::from_name`].
+    pub const NAMES: &'static [&'static str] = &["ns", "us", "ms", "s"];
+
+    /// Get the variant by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ns" => Some(Precision::Nanoseconds),
+            "us" => Some(Precision::Microseconds),
+            "ms" => Some(Precision::Milliseconds),
+            "s" => Some(Precision::Seconds),
+            _ => None,
+        }
+    }
+
+    /// Get the current time.
+    fn now(self) -> i64 {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let time = match self {
+            Precision::Nanoseconds => elapsed.as_nanos(),
+            Precision::Microseconds => elapsed.as_micros(),
+            Precision::Milliseconds => elapsed.as_millis(),
+            Precision::Seconds => u128::from(elapsed.as_secs()),
+        };
+
+        time as i64
+    }
+}
+
+/// InfluxDB line protocol parser.
+#[derive(Debug)]
+pub struct InfluxParser {
+    ignore_tags: bool,
+    precision: Precision,
+}
+
+impl InfluxParser {
+    /// Create a line protocol parser.
+    ///
+    /// If `ignore_tags` is set, the dataset is chosen only by the measurement.
+    /// `precision` is used to set the timestamp of lines without one.
+    pub fn new(ignore_tags: bool, precision: Precision) -> Self {
+        Self {
+            ignore_tags,
+            precision,
+        }
+    }
+}
+
+/// Split `s` on `separator`, skipping escaped separators and, if `quotes` is
+/// set, separators inside double quotes.
+fn split_unescaped(s: &str, separator: char, quotes: bool) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    let mut quoted = false;
+
+    for (index, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+
+            continue;
+        }
+
+        match c {
+            '\\' => escaped = true,
+            '"' if quotes => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&s[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+
+    parts
+}
+
+/// Remove backslashes escaping special characters.
+fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(',')) | ('\\', Some('=')) | ('\\', Some(' ')) => {}
+            _ => unescaped.push(c),
+        }
+    }
+
+    Cow::Owned(unescaped)
+}
+
+/// Split the line into the series, the fields and the optional timestamp.
+fn split_line(line: &str) -> Result<(&str, &str, Option<&str>), ParseError> {
+    let sections = split_unescaped(line, ' ', true);
+
+    match sections.as_slice() {
+        [series, fields] => Ok((series, fields, None)),
+        [series, fields, timestamp] => Ok((series, fields, Some(timestamp))),
+        _ => Err(ParseError {}),
+    }
+}
+
+/// The `(key, value)` pairs of the tags of a series.
+type Tags<'a> = Vec<(&'a str, &'a str)>;
+
+/// Split the series into the measurement and the `(key, value)` pairs of
+/// the tags.
+fn split_series(series: &str) -> Result<(&str, Tags<'_>), ParseError> {
+    let parts = split_unescaped(series, ',', false);
+    let mut tags = vec![];
+
+    for tag in &parts[1..] {
+        match split_unescaped(tag, '=', false).as_slice() {
+            [key, value] if !key.is_empty() && !value.is_empty() =>
+                tags.push((*key, *value)),
+            _ => return Err(ParseError {}),
+        }
+    }
+
+    if parts[0].is_empty() {
+        Err(ParseError {})
+    } else {
+        Ok((parts[0], tags))
+    }
+}
+
+/// Parse a field value. Returns `None` for strings, which are skipped.
+///
+/// Unsigned integers greater than `i64::MAX` are stored as floats.
+fn parse_field_value(value: &str) -> Result<Option<Number>, ParseError> {
+    let number = if value.starts_with('"') {
+        return Ok(None);
+    } else if let "t" | "T" | "true" | "True" | "TRUE" = value {
+        Number::Int(1)
+    } else if let "f" | "F" | "false" | "False" | "FALSE" = value {
+        Number::Int(0)
+    } else if let Some(int) = value.strip_suffix('i') {
+        Number::Int(int.parse::<i64>().map_err(|_| ParseError {})?)
+    } else if let Some(uint) = value.strip_suffix('u') {
+        let uint = uint.parse::<u64>().map_err(|_| ParseError {})?;
+
+        // Integers are stored as `i64`, the larger ones can only be floats.
+        i64::try_from(uint).map_or(Number::Float(uint as f64), Number::Int)
+    } else {
+        Number::Float(value.parse::<f64>().map_err(|_| ParseError {})?)
+    };
+
+    Ok(Some(number))
+}
+
+/// Iterator yielding lines of the input, skipping empty lines and comments.
+pub struct InfluxReader<R>(LineReader<R>)
+where
+    R: io::Read;
+
+impl<R> Iterator for InfluxReader<R>
+where
+    R: io::Read,
+{
+    type Item = Result<String, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.find(|line| match line {
+            Ok(line) => {
+                let line = line.trim();
+
+                !line.is_empty() && !line.starts_with('#')
+            }
+            Err(_) => true,
+        })
+    }
+}
+
+impl<R> Parser<R> for InfluxParser
+where
+    R: io::Read,
+{
+    type Input = String;
+
+    type Settings = ();
+
+    const VARIABLE_FIELDS: bool = true;
+
+    type Reader = InfluxReader<R>;
+
+    fn wrap_reader(reader: R, _settings: Self::Settings) -> Self::Reader {
+        InfluxReader(LineReader::new(reader))
+    }
+
+    fn parse<'a>(
+        &'a self,
+        input: &'a Self::Input,
+    ) -> Result<Record<'a>, ParseError> {
+        let (_, fields, timestamp) = split_line(input.trim())?;
+
+        let mut record = HashMap::new();
+
+        for field in split_unescaped(fields, ',', true) {
+            match split_unescaped(field, '=', true).as_slice() {
+                [key, value] if !key.is_empty() =>
+                    if let Some(number) = parse_field_value(value)? {
+                        record.insert(unescape(key), number);
+                    },
+                _ => return Err(ParseError {}),
+            }
+        }
+
+        let time = match timestamp {
+            Some(timestamp) =>
+                timestamp.parse::<i64>().map_err(|_| ParseError {})?,
+            None => self.precision.now(),
+        };
+        record.insert(Cow::Borrowed(TIME_FIELD), Number::Int(time));
+
+        Ok(Record(record))
+    }
+
+    fn dataset<'a>(
+        &'a self,
+        input: &'a Self::Input,
+    ) -> Result<Option<Cow<'a, str>>, ParseError> {
+        let (series, _, _) = split_line(input.trim())?;
+        let (measurement, tags) = split_series(series)?;
+
+        if self.ignore_tags {
+            return Ok(Some(unescape(measurement)));
+        } else if !series.contains('\\') {
+            return Ok(Some(Cow::Borrowed(series)));
+        }
+
+        // The tags are unescaped one by one, the separators are kept.
+        let mut dataset = unescape(measurement).into_owned();
+        for (key, value) in tags {
+            dataset.push(',');
+            dataset.push_str(&unescape(key));
+            dataset.push('=');
+            dataset.push_str(&unescape(value));
+        }
+
+        Ok(Some(Cow::Owned(dataset)))
+    }
+
+    fn labels<'a>(
+        &'a self,
+        input: &'a Self::Input,
+    ) -> Result<LabelPairs<'a>, ParseError> {
+        // Series with different tags share the dataset, so the tags do not
+        // describe it.
+        if self.ignore_tags {
+            return Ok(vec![]);
+        }
+
+        let (series, _, _) = split_line(input.trim())?;
+        let (_, tags) = split_series(series)?;
+
+        Ok(tags
+            .into_iter()
+            .map(|(key, value)| (unescape(key), unescape(value)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use std::net::UdpSocket;
+
+    use super::*;
+
+    use crate::input::UdpReader;
+
+    const FIXTURE: &str = r#"# CPU usage
+cpu,host=server\ 1,region=eu usage=0.64,cores=8i,up=t,name="a b" 1690000000000
+
+cpu,host=server2 usage=1,cores=4u 1690000000001
+"#;
+
+    type Fields = Vec<(String, Number)>;
+
+    fn parse_line(parser: &InfluxParser, line: &String) -> (String, Fields) {
+        let record = Parser::<&[u8]>::parse(parser, line).unwrap();
+        let dataset = Parser::<&[u8]>::dataset(parser, line)
+            .unwrap()
+            .unwrap()
+            .into_owned();
+
+        let mut fields = record
+            .0
+            .into_iter()
+            .map(|(key, number)| (key.into_owned(), number))
+            .collect::<Vec<_>>();
+        fields.sort_by(|a, b| a.0.cmp(&b.0));
+
+        (dataset, fields)
+    }
+
+    #[test]
+    fn test_parse_influx() {
+        let parser = InfluxParser::new(false, Precision::Nanoseconds);
+
+        let records = <InfluxParser as Parser<&[u8]>>::wrap_reader(
+            FIXTURE.as_bytes(),
+            (),
+        )
+        .map(|line| parse_line(&parser, &line.unwrap()))
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            records,
+            vec![
+                (
+                    "cpu,host=server 1,region=eu".to_string(),
+                    vec![
+                        ("cores".to_string(), Number::Int(8)),
+                        ("time".to_string(), Number::Int(1_690_000_000_000)),
+                        ("up".to_string(), Number::Int(1)),
+                        ("usage".to_string(), Number::Float(0.64)),
+                    ]
+                ),
+                (
+                    "cpu,host=server2".to_string(),
+                    vec![
+                        ("cores".to_string(), Number::Int(4)),
+                        ("time".to_string(), Number::Int(1_690_000_000_001)),
+                        ("usage".to_string(), Number::Float(1.0)),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_influx_ignoring_tags() {
+        let parser = InfluxParser::new(true, Precision::Nanoseconds);
+        let input = r"cpu\ load,host=a value=1".to_string();
+
+        let dataset = Parser::<&[u8]>::dataset(&parser, &input).unwrap();
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert_eq!(dataset, Some(Cow::Borrowed("cpu load")));
+        assert_eq!(record["value"], Number::Float(1.0));
+        assert!(record.0.contains_key(TIME_FIELD));
+    }
+
+    #[test]
+    fn test_influx_escaped_dataset() {
+        let parser = InfluxParser::new(false, Precision::Nanoseconds);
+        let input = r"my\ cpu,host=a,dc=eu\,west value=1".to_string();
+
+        let dataset = Parser::<&[u8]>::dataset(&parser, &input).unwrap();
+
+        assert_eq!(dataset, Some(Cow::Borrowed("my cpu,host=a,dc=eu,west")));
+    }
+
+    #[test]
+    fn test_influx_labels() {
+        let input = r"cpu,host=server\ 1,region=eu usage=0.64".to_string();
+
+        let parser = InfluxParser::new(false, Precision::Nanoseconds);
+        let labels = Parser::<&[u8]>::labels(&parser, &input).unwrap();
+
+        assert_eq!(
+            labels,
+            vec![
+                (Cow::Borrowed("host"), Cow::Borrowed("server 1")),
+                (Cow::Borrowed("region"), Cow::Borrowed("eu")),
+            ]
+        );
+
+        let parser = InfluxParser::new(true, Precision::Nanoseconds);
+
+        assert!(Parser::<&[u8]>::labels(&parser, &input).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_influx_uint() {
+        assert_eq!(parse_field_value("7u").unwrap(), Some(Number::Int(7)));
+        assert_eq!(
+            parse_field_value("18446744073709551615u").unwrap(),
+            Some(Number::Float(18_446_744_073_709_551_615.0))
+        );
+        assert!(parse_field_value("-1u").is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_influx() {
+        let parser = InfluxParser::new(false, Precision::Nanoseconds);
+
+        for input in &["cpu", "cpu value=x", "cpu,host value=1", ",a=b v=1"] {
+            let input = input.to_string();
+            let record = Parser::<&[u8]>::parse(&parser, &input);
+            let dataset = Parser::<&[u8]>::dataset(&parser, &input);
+
+            assert!(record.is_err() || dataset.is_err());
+        }
+    }
+
+    #[test]
+    fn test_read_influx_from_udp() {
+        let parser = InfluxParser::new(false, Precision::Nanoseconds);
+        let reader = UdpReader::bind("127.0.0.1:0", true).unwrap();
+        let addr = reader.local_addr().unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(b"mem,host=a used=10i 1", addr).unwrap();
+        sender.send_to(b"mem,host=a used=12i 2", addr).unwrap();
+
+        let records =
+            <InfluxParser as Parser<UdpReader>>::wrap_reader(reader, ())
+                .take(2)
+                .map(|line| parse_line(&parser, &line.unwrap()))
+                .collect::<Vec<_>>();
+
+        assert_eq!(
+            records[1],
+            (
+                "mem,host=a".to_string(),
+                vec![
+                    ("time".to_string(), Number::Int(2)),
+                    ("used".to_string(), Number::Int(12)),
+                ]
+            )
+        );
+    }
+}
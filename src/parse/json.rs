@@ -3,9 +3,9 @@
 use ::serde::Deserialize;
 use serde_json as json;
 
-use std::io::{self, BufRead, BufReader, Lines};
+use std::io;
 
-use super::{record::Record, ParseError, Parser, ReadError};
+use super::{lines::LineReader, record::Record, ParseError, Parser};
 
 /// JSON parser.
 pub struct JsonParser;
@@ -16,29 +16,8 @@ impl JsonParser {
     }
 }
 
-pub struct JsonReader<R>(Lines<BufReader<R>>)
-where
-    R: io::Read;
-
-impl<R> JsonReader<R>
-where
-    R: io::Read,
-{
-    pub fn new(reader: R) -> Self {
-        Self(BufReader::new(reader).lines())
-    }
-}
-
-impl<R> Iterator for JsonReader<R>
-where
-    R: io::Read,
-{
-    type Item = Result<String, ReadError>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|line| line.map_err(|_| ReadError {}))
-    }
-}
+/// Each line of the input is a JSON object.
+pub type JsonReader<R> = LineReader<R>;
 
 impl<R> Parser<R> for JsonParser
 where
@@ -69,13 +48,15 @@ mod test {
     use ::pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::storage::Number;
 
     #[test]
     fn test_parse_json() {
         let contents = r#"{ "a": 1, "b": 2 }"#;
         let parser = JsonParser {};
 
-        let record = parser.parse(contents).unwrap();
+        let contents = contents.to_string();
+        let record = Parser::<&[u8]>::parse(&parser, &contents).unwrap();
 
         assert_eq!(record["a"], Number::Int(1));
         assert_eq!(record["b"], Number::Int(2));
@@ -86,7 +67,8 @@ mod test {
         let contents = r#"{ "a": 1.0, "b": 2.0 }"#;
         let parser = JsonParser {};
 
-        let record = parser.parse(contents).unwrap();
+        let contents = contents.to_string();
+        let record = Parser::<&[u8]>::parse(&parser, &contents).unwrap();
 
         assert_eq!(record["a"], Number::Float(1.0));
         assert_eq!(record["b"], Number::Float(2.0));
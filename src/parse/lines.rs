@@ -0,0 +1,37 @@
+//! Line-based input.
+
+use std::io::{self, BufRead, BufReader, Lines};
+
+use super::ReadError;
+
+/// Iterator yielding lines of the input.
+pub struct LineReader<R>(Lines<BufReader<R>>)
+where
+    R: io::Read;
+
+impl<R> LineReader<R>
+where
+    R: io::Read,
+{
+    /// Wrap the reader, splitting the input into lines.
+    pub fn new(reader: R) -> Self {
+        Self(BufReader::new(reader).lines())
+    }
+}
+
+impl<R> Iterator for LineReader<R>
+where
+    R: io::Read,
+{
+    type Item = Result<String, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|line| {
+            // The invalid line has been consumed anyway.
+            line.map_err(|e| match e.kind() {
+                io::ErrorKind::InvalidData => ReadError::Invalid,
+                _ => ReadError::Io,
+            })
+        })
+    }
+}
@@ -0,0 +1,167 @@
+//! logfmt parsing.
+//!
+//! Each line of the input is a list of `key=value` pairs separated with
+//! whitespace. Values may be quoted, e.g. `msg="step done" loss=0.3`.
+
+use std::io;
+
+use super::{lines::LineReader, record::Record, ParseError, Parser};
+
+/// logfmt parser.
+#[derive(Debug)]
+pub struct LogfmtParser {
+    strict: bool,
+}
+
+impl LogfmtParser {
+    /// Create a logfmt parser.
+    ///
+    /// Only numeric values are stored. If `strict` is set, a line with
+    /// a non-numeric value is invalid. Otherwise such values are skipped.
+    pub fn new(strict: bool) -> Self {
+        Self { strict }
+    }
+}
+
+/// Find the index of the closing quote, skipping escaped ones.
+fn find_closing_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (index, c) in s.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(index),
+            _ => escaped = false,
+        }
+    }
+
+    None
+}
+
+/// Split the line into `(key, value)` pairs. Quotes around the values are
+/// removed. A key without a value is treated as `key=true`.
+fn tokenize(line: &str) -> Result<Vec<(&str, &str)>, ParseError> {
+    let mut pairs = vec![];
+    let mut rest = line.trim_start();
+
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = &rest[key_end..];
+
+        if key.is_empty() {
+            return Err(ParseError {});
+        }
+
+        let value = if rest.starts_with('=') {
+            rest = &rest[1..];
+
+            if rest.starts_with('"') {
+                let end =
+                    find_closing_quote(&rest[1..]).ok_or(ParseError {})?;
+                let value = &rest[1..=end];
+                rest = &rest[end + 2..];
+
+                value
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let value = &rest[..end];
+                rest = &rest[end..];
+
+                value
+            }
+        } else {
+            "true"
+        };
+
+        pairs.push((key, value));
+        rest = rest.trim_start();
+    }
+
+    Ok(pairs)
+}
+
+impl<R> Parser<R> for LogfmtParser
+where
+    R: io::Read,
+{
+    type Input = String;
+
+    type Settings = ();
+
+    type Reader = LineReader<R>;
+
+    fn wrap_reader(reader: R, _settings: Self::Settings) -> Self::Reader {
+        LineReader::new(reader)
+    }
+
+    fn parse<'a>(
+        &'a self,
+        input: &'a Self::Input,
+    ) -> Result<Record<'a>, ParseError> {
+        let mut pairs = tokenize(input)?;
+        let len = pairs.len();
+        pairs.retain(|(_, value)| value.parse::<f64>().is_ok());
+
+        if self.strict && pairs.len() != len {
+            return Err(ParseError {});
+        }
+
+        Record::from_str_pairs(pairs).map_err(|_| ParseError {})
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::storage::Number;
+
+    #[test]
+    fn test_parse_logfmt() {
+        let parser = LogfmtParser::new(false);
+        let input = r#"level=info msg="step \"done\"" step=120 loss="0.331""#
+            .to_string();
+
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert_eq!(record["step"], Number::Int(120));
+        assert_eq!(record["loss"], Number::Float(0.331));
+        assert_eq!(record.0.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_strict_logfmt() {
+        let parser = LogfmtParser::new(true);
+
+        let input = "step=1 lr=1e-4".to_string();
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert_eq!(record["step"], Number::Int(1));
+        assert_eq!(record["lr"], Number::Float(1e-4));
+
+        let input = "step=1 level=info".to_string();
+
+        assert!(Parser::<&[u8]>::parse(&parser, &input).is_err());
+    }
+
+    #[test]
+    fn test_tokenize_logfmt() {
+        let pairs = tokenize(r#"  a=1 flag b="x y" c="" "#).unwrap();
+
+        assert_eq!(
+            pairs,
+            vec![("a", "1"), ("flag", "true"), ("b", "x y"), ("c", "")]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_invalid_logfmt() {
+        assert!(tokenize(r#"a="unterminated"#).is_err());
+        assert!(tokenize("=1").is_err());
+    }
+}
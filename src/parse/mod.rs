@@ -1,13 +1,28 @@
 //! Input parsing.
 
-use std::{collections::HashMap, fmt, io};
+use std::{borrow::Cow, collections::HashMap, fmt, io};
 
+pub mod arrow;
+pub mod binary;
+pub mod cbor;
 pub mod csv;
+pub mod framed;
+pub mod influx;
 pub mod json;
+pub mod lines;
+pub mod logfmt;
+pub mod msgpack;
+pub mod prometheus;
 pub mod record;
+pub mod regex;
 
-use crate::parse::record::Record;
-use crate::storage::Number;
+use crate::parse::{
+    binary::Endian,
+    framed::Framing,
+    influx::Precision,
+    record::Record,
+};
+use crate::storage::{Number, NumberVec};
 
 /// Parser settings.
 pub enum ParserSettings {
@@ -18,6 +33,80 @@ pub enum ParserSettings {
     },
     /// Create a JSON parser.
     Json,
+    /// Create a MessagePack parser from framing of the records.
+    Msgpack {
+        /// How the records are delimited.
+        framing: Framing,
+    },
+    /// Create a CBOR parser from framing of the records.
+    Cbor {
+        /// How the records are delimited.
+        framing: Framing,
+    },
+    /// Create a binary parser from layout and byte order.
+    Binary {
+        /// Names and types of the fields, e.g. `t:f64,x:f32`.
+        layout: String,
+        /// Byte order of the numbers.
+        endian: Endian,
+    },
+    /// Create an Arrow IPC stream parser.
+    Arrow,
+    /// Create a regex parser from a pattern with named capture groups.
+    Regex {
+        /// The pattern.
+        pattern: String,
+        /// Whether lines which do not match the pattern are skipped.
+        skip_unmatched: bool,
+    },
+    /// Create a logfmt parser.
+    Logfmt {
+        /// Whether lines with non-numeric values are invalid.
+        strict: bool,
+    },
+    /// Create an InfluxDB line protocol parser.
+    Influx {
+        /// Whether the measurement alone chooses the dataset.
+        ignore_tags: bool,
+        /// The unit of the timestamps.
+        precision: Precision,
+    },
+    /// Create a Prometheus text format parser from the chosen series.
+    Prometheus {
+        /// The series to store. Empty means all of them.
+        series: Vec<String>,
+    },
+}
+
+/// Labels of a dataset as `(name, value)` pairs, see [`Parser::labels`].
+pub type LabelPairs<'a> = Vec<(Cow<'a, str>, Cow<'a, str>)>;
+
+/// Columns of many records parsed at once, e.g. of an Arrow record batch.
+#[derive(Debug)]
+pub struct Columns {
+    /// The values of the valid records, by field.
+    pub columns: HashMap<String, NumberVec>,
+    /// The number of valid records.
+    pub len: usize,
+    /// The number of records which could not be parsed.
+    pub rejected: usize,
+}
+
+impl Columns {
+    /// Iterate over the valid records.
+    pub fn records(&self) -> impl Iterator<Item = Record<'_>> {
+        (0..self.len).map(move |index| {
+            let record = self
+                .columns
+                .iter()
+                .map(|(field, column)| {
+                    (Cow::Borrowed(field.as_str()), column.get(index).unwrap())
+                })
+                .collect();
+
+            Record(record)
+        })
+    }
 }
 
 /// Parse error.
@@ -34,12 +123,31 @@ impl fmt::Display for ParseError {
     }
 }
 
-#[derive(Debug)]
-pub struct ReadError;
+/// Read error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadError {
+    /// A record could not be read, e.g. a line is not valid UTF-8 or a frame
+    /// is too long. The reader has skipped it, so the following records can
+    /// still be read.
+    Invalid,
+    /// The input could not be read any further, e.g. the connection has been
+    /// reset or the end of a corrupted record could not be found.
+    Io,
+}
+
+impl ReadError {
+    /// Check whether the following records can still be read.
+    pub fn is_recoverable(self) -> bool {
+        self == ReadError::Invalid
+    }
+}
 
 impl fmt::Display for ReadError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "read error")
+        match self {
+            ReadError::Invalid => write!(f, "invalid record"),
+            ReadError::Io => write!(f, "read error"),
+        }
     }
 }
 
@@ -56,7 +164,19 @@ where
     type Input: Send;
 
     /// Reader settings.
-    type Settings: Send;
+    ///
+    /// The settings are cloned for every reader, i.e. every HTTP request
+    /// body is wrapped with its own copy.
+    type Settings: Send + Sync + Clone;
+
+    /// Whether the input consists of lines. Datagrams received over UDP
+    /// are terminated with a newline only for such formats.
+    const LINE_BASED: bool = true;
+
+    /// Whether the records of a dataset may carry different fields, e.g. the
+    /// points of an InfluxDB series. The missing values are then stored as
+    /// NaN instead of making the record invalid.
+    const VARIABLE_FIELDS: bool = false;
 
     /// Iterator yielding [`Self::Input`][Parser::Input].
     type Reader: Iterator<Item = Result<Self::Input, ReadError>>;
@@ -69,4 +189,40 @@ where
         &'a self,
         input: &'a Self::Input,
     ) -> Result<Record<'a>, ParseError>;
+
+    /// Parse the input holding many records at once into columns, which are
+    /// appended to the storage as a whole.
+    ///
+    /// `None` means the input holds a single record and is parsed with
+    /// [`parse`][Parser::parse]. Invalid records are only counted, so they
+    /// do not invalidate the rest of the input.
+    fn parse_columns(
+        &self,
+        _input: &Self::Input,
+    ) -> Option<Result<Columns, ParseError>> {
+        None
+    }
+
+    /// Get the dataset the input belongs to.
+    ///
+    /// `None` means the dataset of the source the input has been read from.
+    /// Most formats do not carry any information about the dataset.
+    fn dataset<'a>(
+        &'a self,
+        _input: &'a Self::Input,
+    ) -> Result<Option<Cow<'a, str>>, ParseError> {
+        Ok(None)
+    }
+
+    /// Get the labels of the dataset the input belongs to, as `(name,
+    /// value)` pairs.
+    ///
+    /// Only called for the first input of each dataset. Most formats do not
+    /// carry any labels.
+    fn labels<'a>(
+        &'a self,
+        _input: &'a Self::Input,
+    ) -> Result<LabelPairs<'a>, ParseError> {
+        Ok(vec![])
+    }
 }
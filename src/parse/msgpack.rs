@@ -0,0 +1,138 @@
+//! MessagePack parsing.
+
+use ::rmp_serde::Deserializer as MsgpackDeserializer;
+use ::serde::{de::IgnoredAny, Deserialize};
+
+use std::io::{self, Read};
+
+use super::{
+    framed::{FramedReader, Framing},
+    record::Record,
+    ParseError,
+    Parser,
+    ReadError,
+};
+
+/// MessagePack parser.
+pub struct MsgpackParser;
+
+impl MsgpackParser {
+    /// Create a MessagePack parser.
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+fn skip_value(reader: &mut dyn Read) -> Result<(), ReadError> {
+    IgnoredAny::deserialize(&mut MsgpackDeserializer::new(reader))
+        .map(|_| ())
+        // The end of the invalid value is unknown, so the following ones
+        // cannot be found.
+        .map_err(|_| ReadError::Io)
+}
+
+impl<R> Parser<R> for MsgpackParser
+where
+    R: io::Read,
+{
+    type Input = Vec<u8>;
+
+    type Settings = Framing;
+
+    const LINE_BASED: bool = false;
+
+    type Reader = FramedReader<R>;
+
+    fn wrap_reader(reader: R, settings: Self::Settings) -> Self::Reader {
+        FramedReader::new(reader, settings, skip_value)
+    }
+
+    fn parse<'a>(
+        &'a self,
+        input: &'a Self::Input,
+    ) -> Result<Record<'a>, ParseError> {
+        let mut deserializer = MsgpackDeserializer::from_read_ref(input);
+
+        Record::deserialize(&mut deserializer).map_err(|_| ParseError {})
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use std::collections::HashMap;
+
+    use super::*;
+
+    use crate::{parse::framed::MAX_FRAME_SIZE, storage::Number};
+
+    fn encode(a: i64, b: f64) -> Vec<u8> {
+        let mut record = HashMap::new();
+        record.insert("a", Number::Int(a));
+        record.insert("b", Number::Float(b));
+
+        ::rmp_serde::to_vec(&record).unwrap()
+    }
+
+    #[test]
+    fn test_parse_msgpack() {
+        let input = encode(1, 2.0);
+        let parser = MsgpackParser::new();
+
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert_eq!(record["a"], Number::Int(1));
+        assert_eq!(record["b"], Number::Float(2.0));
+    }
+
+    #[test]
+    fn test_read_self_delimiting_msgpack() {
+        let record_1 = encode(1, 2.0);
+        let record_2 = encode(11, 12.0);
+        let mut contents = record_1.clone();
+        contents.extend(&record_2);
+
+        let mut reader = <MsgpackParser as Parser<&[u8]>>::wrap_reader(
+            &contents[..],
+            Framing::SelfDelimiting,
+        );
+
+        assert_eq!(reader.next().unwrap().unwrap(), record_1);
+        assert_eq!(reader.next().unwrap().unwrap(), record_2);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_read_length_delimited_msgpack() {
+        let record = encode(1, 2.0);
+        let mut contents = (record.len() as u32).to_be_bytes().to_vec();
+        contents.extend(&record);
+
+        let mut reader = <MsgpackParser as Parser<&[u8]>>::wrap_reader(
+            &contents[..],
+            Framing::LengthDelimited,
+        );
+
+        assert_eq!(reader.next().unwrap().unwrap(), record);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_read_too_long_msgpack() {
+        let record = encode(1, 2.0);
+        let mut contents = (MAX_FRAME_SIZE as u32 + 1).to_be_bytes().to_vec();
+        contents.extend(vec![0; MAX_FRAME_SIZE + 1]);
+        contents.extend(&(record.len() as u32).to_be_bytes());
+        contents.extend(&record);
+
+        let mut reader = <MsgpackParser as Parser<&[u8]>>::wrap_reader(
+            &contents[..],
+            Framing::LengthDelimited,
+        );
+
+        assert_eq!(reader.next().unwrap().unwrap_err(), ReadError::Invalid);
+        assert_eq!(reader.next().unwrap().unwrap(), record);
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,282 @@
+//! Prometheus text exposition format parsing.
+//!
+//! Each scrape (a sequence of samples ended with an `# EOF` line or with the
+//! end of the input) becomes a single record. The series become the fields
+//! of the record and the time of the scrape is stored in the `time` field.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{lines::LineReader, record::Record, ParseError, Parser, ReadError};
+use crate::storage::Number;
+
+/// The name of the field the time of the scrape is stored in.
+pub const TIME_FIELD: &str = "time";
+
+/// The line ending a scrape in the OpenMetrics format.
+const EOF_LINE: &str = "# EOF";
+
+/// Samples of a single scrape.
+#[derive(Debug)]
+pub struct Scrape {
+    /// The time the scrape has been read at, in milliseconds.
+    pub time: i64,
+    /// The sample lines.
+    pub samples: Vec<String>,
+}
+
+/// Prometheus text format parser.
+#[derive(Debug)]
+pub struct PrometheusParser {
+    series: Vec<String>,
+}
+
+impl PrometheusParser {
+    /// Create a Prometheus parser.
+    ///
+    /// Only the chosen series are stored. A series can be chosen by its
+    /// metric name or by its name and labels as written in the scrape,
+    /// e.g. `http_requests_total{code="200"}`. If no series are chosen, all
+    /// of them are stored.
+    pub fn new(series: Vec<String>) -> Self {
+        Self { series }
+    }
+
+    fn is_selected(&self, series: &str) -> bool {
+        let name = series.split('{').next().unwrap_or(series);
+
+        self.series.is_empty()
+            || self
+                .series
+                .iter()
+                .any(|selected| selected == series || selected == name)
+    }
+}
+
+/// Split the sample line into the series and the value.
+fn split_sample(line: &str) -> Result<(&str, &str), ParseError> {
+    let end = match line.find(|c: char| c == '{' || c.is_whitespace()) {
+        Some(0) | None => return Err(ParseError {}),
+        Some(index) if line[index..].starts_with('{') => {
+            // Label values are quoted and may contain braces or spaces.
+            let mut quoted = false;
+            let mut escaped = false;
+            let mut end = None;
+
+            for (offset, c) in line[index..].char_indices() {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => quoted = !quoted,
+                    '}' if !quoted => {
+                        end = Some(index + offset + 1);
+
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            end.ok_or(ParseError {})?
+        }
+        Some(index) => index,
+    };
+
+    let series = &line[..end];
+    let value = line[end..].split_whitespace().next().ok_or(ParseError {})?;
+
+    Ok((series, value))
+}
+
+/// Iterator yielding scrapes.
+pub struct PrometheusReader<R>(LineReader<R>)
+where
+    R: io::Read;
+
+impl<R> Iterator for PrometheusReader<R>
+where
+    R: io::Read,
+{
+    type Item = Result<Scrape, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut samples = vec![];
+
+        loop {
+            match self.0.next() {
+                Some(Ok(line)) => {
+                    let line = line.trim();
+
+                    if line == EOF_LINE {
+                        break;
+                    } else if !line.is_empty() && !line.starts_with('#') {
+                        samples.push(line.to_string());
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None if samples.is_empty() => return None,
+                None => break,
+            }
+        }
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+
+        Some(Ok(Scrape { time, samples }))
+    }
+}
+
+impl<R> Parser<R> for PrometheusParser
+where
+    R: io::Read,
+{
+    type Input = Scrape;
+
+    type Settings = ();
+
+    const VARIABLE_FIELDS: bool = true;
+
+    type Reader = PrometheusReader<R>;
+
+    fn wrap_reader(reader: R, _settings: Self::Settings) -> Self::Reader {
+        PrometheusReader(LineReader::new(reader))
+    }
+
+    fn parse<'a>(
+        &'a self,
+        input: &'a Self::Input,
+    ) -> Result<Record<'a>, ParseError> {
+        let mut record = HashMap::new();
+
+        for line in &input.samples {
+            let (series, value) = split_sample(line)?;
+
+            if self.is_selected(series) {
+                let value = value.parse::<f64>().map_err(|_| ParseError {})?;
+                record.insert(Cow::Borrowed(series), Number::Float(value));
+            }
+        }
+        record.insert(Cow::Borrowed(TIME_FIELD), Number::Int(input.time));
+
+        Ok(Record(record))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const FIXTURE: &str = r#"# HELP http_requests_total The total number of requests.
+# TYPE http_requests_total counter
+http_requests_total{method="post",path="/a b{}"} 1027 1395066363000
+http_requests_total{method="get"} 3
+process_cpu_seconds_total 12.47
+# EOF
+http_requests_total{method="post",path="/a b{}"} 1030
+http_requests_total{method="get"} 5
+process_cpu_seconds_total 12.5
+"#;
+
+    fn parse_scrapes(parser: &PrometheusParser) -> Vec<Vec<(String, Number)>> {
+        <PrometheusParser as Parser<&[u8]>>::wrap_reader(FIXTURE.as_bytes(), ())
+            .map(|scrape| {
+                let scrape = scrape.unwrap();
+                let record = Parser::<&[u8]>::parse(parser, &scrape).unwrap();
+
+                let mut fields = record
+                    .0
+                    .into_iter()
+                    .filter(|(key, _)| key != TIME_FIELD)
+                    .map(|(key, number)| (key.into_owned(), number))
+                    .collect::<Vec<_>>();
+                fields.sort_by(|a, b| a.0.cmp(&b.0));
+
+                fields
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_prometheus() {
+        let parser = PrometheusParser::new(vec![]);
+
+        assert_eq!(
+            parse_scrapes(&parser),
+            vec![
+                vec![
+                    (
+                        r#"http_requests_total{method="get"}"#.to_string(),
+                        Number::Float(3.0)
+                    ),
+                    (
+                        r#"http_requests_total{method="post",path="/a b{}"}"#
+                            .to_string(),
+                        Number::Float(1027.0)
+                    ),
+                    (
+                        "process_cpu_seconds_total".to_string(),
+                        Number::Float(12.47)
+                    ),
+                ],
+                vec![
+                    (
+                        r#"http_requests_total{method="get"}"#.to_string(),
+                        Number::Float(5.0)
+                    ),
+                    (
+                        r#"http_requests_total{method="post",path="/a b{}"}"#
+                            .to_string(),
+                        Number::Float(1030.0)
+                    ),
+                    (
+                        "process_cpu_seconds_total".to_string(),
+                        Number::Float(12.5)
+                    ),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_selected_series() {
+        let parser = PrometheusParser::new(vec![
+            r#"http_requests_total{method="get"}"#.to_string(),
+            "process_cpu_seconds_total".to_string(),
+        ]);
+
+        let scrapes = parse_scrapes(&parser);
+
+        assert_eq!(
+            scrapes[1],
+            vec![
+                (
+                    r#"http_requests_total{method="get"}"#.to_string(),
+                    Number::Float(5.0)
+                ),
+                ("process_cpu_seconds_total".to_string(), Number::Float(12.5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_prometheus() {
+        let parser = PrometheusParser::new(vec![]);
+
+        for line in &["up", "up{job=\"a\" 1", "up x", "{job=\"a\"} 1"] {
+            let scrape = Scrape {
+                time: 0,
+                samples: vec![line.to_string()],
+            };
+
+            assert!(Parser::<&[u8]>::parse(&parser, &scrape).is_err());
+        }
+    }
+}
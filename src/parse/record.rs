@@ -1,7 +1,16 @@
 //! Record deserialization.
 
 use ::serde::{
-    de::{self, MapAccess, Visitor},
+    de::{
+        self,
+        value::{
+            BorrowedStrDeserializer,
+            Error as ValueError,
+            MapDeserializer,
+        },
+        MapAccess,
+        Visitor,
+    },
     Deserialize,
     Deserializer,
     Serialize,
@@ -305,6 +314,25 @@ impl<'a, 'de: 'a> Deserialize<'de> for Record<'a> {
     }
 }
 
+impl<'a> Record<'a> {
+    /// Create a record from pairs of field names and values numbers are
+    /// parsed from.
+    pub fn from_str_pairs<I>(pairs: I) -> Result<Self, DeserError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let pairs = pairs.into_iter().map(|(key, value)| {
+            (
+                BorrowedStrDeserializer::new(key),
+                BorrowedStrDeserializer::new(value),
+            )
+        });
+
+        Record::deserialize(MapDeserializer::<_, ValueError>::new(pairs))
+            .map_err(|_| DeserError::Parse)
+    }
+}
+
 impl<'a, S> std::ops::Index<S> for Record<'a>
 where
     S: std::borrow::Borrow<str>,
@@ -0,0 +1,134 @@
+//! Regex parsing.
+//!
+//! Each line of the input is matched against a pattern with named capture
+//! groups. The names of the groups become the names of the fields.
+
+use ::regex::Regex;
+
+use std::{collections::HashMap, io};
+
+use super::{lines::LineReader, record::Record, ParseError, Parser};
+
+/// Regex parser.
+#[derive(Debug)]
+pub struct RegexParser {
+    regex: Regex,
+    skip_unmatched: bool,
+}
+
+impl RegexParser {
+    /// Create a regex parser. Returns `None` if the pattern has no named
+    /// capture groups.
+    ///
+    /// Every named group has to match. If `skip_unmatched` is set, a line
+    /// which does not match the pattern is skipped. Otherwise it is invalid.
+    pub fn new(regex: Regex, skip_unmatched: bool) -> Option<Self> {
+        if regex.capture_names().flatten().next().is_some() {
+            Some(Self {
+                regex,
+                skip_unmatched,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl<R> Parser<R> for RegexParser
+where
+    R: io::Read,
+{
+    type Input = String;
+
+    type Settings = ();
+
+    type Reader = LineReader<R>;
+
+    fn wrap_reader(reader: R, _settings: Self::Settings) -> Self::Reader {
+        LineReader::new(reader)
+    }
+
+    fn parse<'a>(
+        &'a self,
+        input: &'a Self::Input,
+    ) -> Result<Record<'a>, ParseError> {
+        let captures = match self.regex.captures(input) {
+            Some(captures) => captures,
+            // An empty record is counted as filtered out.
+            None if self.skip_unmatched => return Ok(Record(HashMap::new())),
+            None => return Err(ParseError {}),
+        };
+        let pairs = self
+            .regex
+            .capture_names()
+            .flatten()
+            .map(|name| {
+                captures
+                    .name(name)
+                    .map(|value| (name, value.as_str()))
+                    .ok_or(ParseError {})
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Record::from_str_pairs(pairs).map_err(|_| ParseError {})
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use super::*;
+
+    use crate::storage::Number;
+
+    fn create_parser(skip_unmatched: bool) -> RegexParser {
+        let regex =
+            Regex::new(r"step=(?P<step>\d+) loss=(?P<loss>\S+)").unwrap();
+
+        RegexParser::new(regex, skip_unmatched).unwrap()
+    }
+
+    #[test]
+    fn test_parse_regex() {
+        let parser = create_parser(false);
+        let input = "step=120 loss=0.331 lr=1e-4".to_string();
+
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert_eq!(record["step"], Number::Int(120));
+        assert_eq!(record["loss"], Number::Float(0.331));
+        assert_eq!(record.0.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_unmatched_line() {
+        let input = "epoch finished".to_string();
+
+        let parser = create_parser(false);
+
+        assert!(Parser::<&[u8]>::parse(&parser, &input).is_err());
+
+        let parser = create_parser(true);
+        let record = Parser::<&[u8]>::parse(&parser, &input).unwrap();
+
+        assert!(record.0.is_empty());
+    }
+
+    #[test]
+    fn test_parse_unmatched_optional_group() {
+        let regex =
+            Regex::new(r"step=(?P<step>\d+)( loss=(?P<loss>\S+))?").unwrap();
+        let parser = RegexParser::new(regex, true).unwrap();
+        let input = "step=120".to_string();
+
+        assert!(Parser::<&[u8]>::parse(&parser, &input).is_err());
+    }
+
+    #[test]
+    fn test_pattern_without_named_groups() {
+        assert!(
+            RegexParser::new(Regex::new(r"(\d+)").unwrap(), false).is_none()
+        );
+    }
+}
@@ -1,19 +1,85 @@
 pub use crate::{
+    export::export_arrow,
+    expr::{Expr, ExprError, Scope},
+    input::{
+        decompress,
+        AutoDecoder,
+        Compression,
+        FollowReader,
+        InputSource,
+        ScrapeReader,
+        Source,
+        UdpReader,
+    },
     parse::{
+        arrow::ArrowParser,
+        binary::{BinaryParser, Endian},
+        cbor::CborParser,
         csv::CsvParser,
+        framed::Framing,
+        influx::{InfluxParser, Precision},
         json::JsonParser,
+        lines::LineReader,
+        logfmt::LogfmtParser,
+        msgpack::MsgpackParser,
+        prometheus::{PrometheusParser, Scrape},
         record::{DeserError, Record},
+        regex::RegexParser,
+        Columns,
+        LabelPairs,
         ParseError,
         Parser,
         ParserSettings,
         ReadError,
     },
+    render::{
+        render,
+        render_png,
+        render_svg,
+        render_terminal,
+        ImageFormat,
+        Mark,
+        RenderError,
+        RenderSettings,
+        MAX_IMAGE_SIZE,
+    },
     server::{
+        get_arrow_export,
+        get_client_index,
+        get_client_script,
+        get_events,
+        get_png_render,
+        get_svg_render,
+        post_records,
         ws_handshake,
+        EndOfInput,
+        IngestMessage,
+        IngestReport,
         InputMessage,
+        RejectedInput,
+        RenderTarget,
         Server,
         StopAppMessage,
+        TerminalSession,
+        TerminalView,
+        TokenAuth,
         WsSessionState,
+        DEFAULT_DATASET,
+    },
+    storage::{Labels, Number, Storage, StorageChunk},
+    tls::{load_tls_config, TlsError},
+    transform::{
+        Aggregate,
+        Derivation,
+        Rename,
+        Resampling,
+        Rolling,
+        TimeField,
+        TimeUnit,
+        Transform,
+        TransformError,
+        TransformSettings,
+        Window,
+        RESAMPLED_SUFFIX,
     },
-    storage::{Number, Storage},
 };
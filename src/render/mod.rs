@@ -0,0 +1,690 @@
+//! Chart rendering.
+
+use ::png::{BitDepth, ColorType, Encoder, EncodingError};
+
+use std::{fmt, fmt::Write, path::Path};
+
+use crate::storage::Storage;
+
+mod terminal;
+
+pub use self::terminal::render_terminal;
+
+/// Colors of the series, in the order of the fields.
+const COLORS: &[[u8; 3]] = &[
+    [0x1f, 0x77, 0xb4],
+    [0xff, 0x7f, 0x0e],
+    [0x2c, 0xa0, 0x2c],
+    [0xd6, 0x27, 0x28],
+    [0x94, 0x67, 0xbd],
+    [0x8c, 0x56, 0x4b],
+    [0xe3, 0x77, 0xc2],
+    [0x7f, 0x7f, 0x7f],
+    [0xbc, 0xbd, 0x22],
+    [0x17, 0xbe, 0xcf],
+];
+
+/// Color of the frame around the plot area.
+const FRAME_COLOR: [u8; 3] = [0xcc, 0xcc, 0xcc];
+
+/// Space left around the plot area for the labels and the legend.
+const PADDING_TOP: f64 = 24.0;
+const PADDING_RIGHT: f64 = 16.0;
+const PADDING_BOTTOM: f64 = 32.0;
+const PADDING_LEFT: f64 = 64.0;
+
+/// Maximal width and height of an image in pixels.
+pub const MAX_IMAGE_SIZE: u32 = 4096;
+
+/// How the values are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mark {
+    /// Consecutive values joined with lines.
+    Line,
+    /// Each value drawn as a dot.
+    Scatter,
+}
+
+impl Mark {
+    /// Names of the variants accepted by [`Mark::from_name`].
+    pub const NAMES: &'static [&'static str] = &["line", "scatter"];
+
+    /// Get the variant by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "line" => Some(Mark::Line),
+            "scatter" => Some(Mark::Scatter),
+            _ => None,
+        }
+    }
+}
+
+/// Format of the rendered image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    /// SVG document.
+    Svg,
+    /// PNG image. Unlike SVG, it has no labels or legend.
+    Png,
+}
+
+impl ImageFormat {
+    /// Guess the format from the extension of the path. Anything but `.png`
+    /// is rendered as SVG.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("png") => ImageFormat::Png,
+            _ => ImageFormat::Svg,
+        }
+    }
+
+    /// Get the MIME type of the format.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "image/svg+xml",
+            ImageFormat::Png => "image/png",
+        }
+    }
+}
+
+/// Render settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    /// The field on the x axis. `None` means the index of the record.
+    pub x: Option<String>,
+    /// The fields on the y axis. `None` means all fields but `x`.
+    pub y: Option<Vec<String>>,
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// How the values are drawn.
+    pub mark: Mark,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            x: None,
+            y: None,
+            width: 800,
+            height: 600,
+            mark: Mark::Line,
+        }
+    }
+}
+
+/// Render error.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The field does not exist in the storage.
+    NoField(String),
+    /// The image is too small to fit the plot area.
+    TooSmall,
+    /// The image is wider or higher than [`MAX_IMAGE_SIZE`].
+    TooLarge,
+    /// The PNG image could not be encoded.
+    Png(EncodingError),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::NoField(field) => write!(f, "no field `{}`", field),
+            RenderError::TooSmall => write!(f, "the image is too small"),
+            RenderError::TooLarge => write!(f, "the image is too large"),
+            RenderError::Png(error) => write!(f, "png error: {}", error),
+        }
+    }
+}
+
+/// The part of the image the values are drawn on.
+struct Area {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Area {
+    /// Get the plot area of the image, leaving space for the labels and
+    /// the legend.
+    fn of_image(settings: &RenderSettings) -> Result<Self, RenderError> {
+        let width = f64::from(settings.width);
+        let height = f64::from(settings.height);
+
+        if settings.width > MAX_IMAGE_SIZE || settings.height > MAX_IMAGE_SIZE {
+            Err(RenderError::TooLarge)
+        } else if width <= PADDING_LEFT + PADDING_RIGHT
+            || height <= PADDING_TOP + PADDING_BOTTOM
+        {
+            Err(RenderError::TooSmall)
+        } else {
+            Ok(Self {
+                left: PADDING_LEFT,
+                top: PADDING_TOP,
+                width: width - PADDING_LEFT - PADDING_RIGHT,
+                height: height - PADDING_TOP - PADDING_BOTTOM,
+            })
+        }
+    }
+}
+
+/// A field scaled to the image coordinates.
+struct Series {
+    name: String,
+    color: [u8; 3],
+    /// `None` for the records with a non-finite value.
+    points: Vec<Option<(f64, f64)>>,
+}
+
+/// The fields of the storage laid out on the image.
+struct Chart {
+    x_name: String,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    series: Vec<Series>,
+}
+
+impl Chart {
+    fn new(
+        storage: &Storage,
+        settings: &RenderSettings,
+        area: &Area,
+    ) -> Result<Self, RenderError> {
+        let column = |field: &str| -> Result<Vec<f64>, RenderError> {
+            storage
+                .columns()
+                .find(|(name, _)| *name == field)
+                .map(|(_, column)| {
+                    (0..column.len())
+                        .map(|i| column.get(i).unwrap().as_f64())
+                        .collect()
+                })
+                .ok_or_else(|| RenderError::NoField(field.to_string()))
+        };
+
+        let xs = match &settings.x {
+            Some(field) => column(field)?,
+            None => (0..storage.records_len()).map(|i| i as f64).collect(),
+        };
+        let y_fields = match &settings.y {
+            Some(fields) => fields.clone(),
+            None => {
+                let mut fields = storage
+                    .columns()
+                    .map(|(field, _)| field.to_string())
+                    .filter(|field| Some(field) != settings.x.as_ref())
+                    .collect::<Vec<_>>();
+                fields.sort();
+
+                fields
+            }
+        };
+        let ys = y_fields
+            .iter()
+            .map(|field| column(field))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let x_range = range(xs.iter());
+        let y_range = range(ys.iter().flatten());
+
+        let scale = |value: f64, (min, max): (f64, f64), len: f64| {
+            (value - min) / (max - min) * len
+        };
+        let series = y_fields
+            .into_iter()
+            .zip(ys)
+            .enumerate()
+            .map(|(i, (name, ys))| {
+                let points = xs
+                    .iter()
+                    .zip(ys)
+                    .map(|(x, y)| {
+                        if x.is_finite() && y.is_finite() {
+                            Some((
+                                area.left + scale(*x, x_range, area.width),
+                                area.top + area.height
+                                    - scale(y, y_range, area.height),
+                            ))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                Series {
+                    name,
+                    color: COLORS[i % COLORS.len()],
+                    points,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            x_name: settings.x.clone().unwrap_or_else(|| "index".to_string()),
+            x_range,
+            y_range,
+            series,
+        })
+    }
+}
+
+/// Something the values can be drawn on.
+trait Surface {
+    /// Set the color of the pixel. Pixels outside of the surface are ignored.
+    fn set(&mut self, x: i64, y: i64, color: [u8; 3]);
+
+    /// Draw a line with the Bresenham's algorithm.
+    fn line(
+        &mut self,
+        (x0, y0): (f64, f64),
+        (x1, y1): (f64, f64),
+        color: [u8; 3],
+    ) {
+        let (mut x, mut y) = (x0.round() as i64, y0.round() as i64);
+        let (x1, y1) = (x1.round() as i64, y1.round() as i64);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+        let mut error = dx + dy;
+
+        loop {
+            self.set(x, y, color);
+
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += sx;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Draw a dot of the given radius.
+    fn dot(&mut self, (x, y): (f64, f64), radius: i64, color: [u8; 3]) {
+        let (x, y) = (x.round() as i64, y.round() as i64);
+
+        for dx in -radius..=radius {
+            for dy in -radius..=radius {
+                self.set(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    /// Draw the series of the chart.
+    fn series(&mut self, chart: &Chart, mark: Mark, radius: i64) {
+        for series in &chart.series {
+            match mark {
+                Mark::Line =>
+                    for pair in series.points.windows(2) {
+                        if let (Some(from), Some(to)) = (pair[0], pair[1]) {
+                            self.line(from, to, series.color);
+                        }
+                    },
+                Mark::Scatter =>
+                    for point in series.points.iter().flatten() {
+                        self.dot(*point, radius, series.color);
+                    },
+            }
+        }
+    }
+}
+
+/// Get the range of the finite values. The range is never empty.
+fn range<'a, I>(values: I) -> (f64, f64)
+where
+    I: Iterator<Item = &'a f64>,
+{
+    let (min, max) = values
+        .filter(|value| value.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(*value), max.max(*value))
+        });
+
+    if min > max {
+        (0.0, 1.0)
+    } else if min == max {
+        (min - 1.0, max + 1.0)
+    } else {
+        (min, max)
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value != 0.0 && (value.abs() >= 1e5 || value.abs() < 1e-3) {
+        format!("{:.2e}", value)
+    } else {
+        format!("{}", (value * 1e3).round() / 1e3)
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn css_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Render the fields of the storage as an SVG document.
+pub fn render_svg(
+    storage: &Storage,
+    settings: &RenderSettings,
+) -> Result<String, RenderError> {
+    let chart = Chart::new(storage, settings, &Area::of_image(settings)?)?;
+    let width = f64::from(settings.width);
+    let height = f64::from(settings.height);
+    let plot_right = width - PADDING_RIGHT;
+    let plot_bottom = height - PADDING_BOTTOM;
+
+    let mut svg = String::new();
+    // Writing to a `String` never fails.
+    let _ = write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" \
+         height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" \
+         font-size=\"12\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\
+         <rect x=\"{l}\" y=\"{t}\" width=\"{pw}\" height=\"{ph}\" \
+         fill=\"none\" stroke=\"{frame}\"/>",
+        w = settings.width,
+        h = settings.height,
+        l = PADDING_LEFT,
+        t = PADDING_TOP,
+        pw = plot_right - PADDING_LEFT,
+        ph = plot_bottom - PADDING_TOP,
+        frame = css_color(FRAME_COLOR),
+    );
+
+    let mut label = |x: f64, y: f64, anchor: &str, text: &str| {
+        let _ = write!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"{}\" fill=\"#666666\">{}\
+             </text>",
+            x,
+            y,
+            anchor,
+            escape_xml(text)
+        );
+    };
+    label(
+        PADDING_LEFT - 4.0,
+        PADDING_TOP + 12.0,
+        "end",
+        &format_number(chart.y_range.1),
+    );
+    label(
+        PADDING_LEFT - 4.0,
+        plot_bottom,
+        "end",
+        &format_number(chart.y_range.0),
+    );
+    label(
+        PADDING_LEFT,
+        height - 12.0,
+        "start",
+        &format_number(chart.x_range.0),
+    );
+    label(
+        plot_right,
+        height - 12.0,
+        "end",
+        &format_number(chart.x_range.1),
+    );
+    label(
+        (PADDING_LEFT + plot_right) / 2.0,
+        height - 12.0,
+        "middle",
+        &chart.x_name,
+    );
+
+    let mut legend_x = PADDING_LEFT;
+    for series in &chart.series {
+        let color = css_color(series.color);
+        let _ = write!(
+            svg,
+            "<text x=\"{}\" y=\"16\" fill=\"{}\">{}</text>",
+            legend_x,
+            color,
+            escape_xml(&series.name)
+        );
+        legend_x += 16.0 + 7.0 * series.name.chars().count() as f64;
+
+        match settings.mark {
+            Mark::Line =>
+                for line in series.points.split(|point| point.is_none()) {
+                    let points = line
+                        .iter()
+                        .flatten()
+                        .map(|(x, y)| format!("{:.2},{:.2}", x, y))
+                        .collect::<Vec<_>>();
+
+                    if !points.is_empty() {
+                        let _ = write!(
+                            svg,
+                            "<polyline fill=\"none\" stroke=\"{}\" \
+                             points=\"{}\"/>",
+                            color,
+                            points.join(" ")
+                        );
+                    }
+                },
+            Mark::Scatter => {
+                let _ = write!(svg, "<g fill=\"{}\">", color);
+                for (x, y) in series.points.iter().flatten() {
+                    let _ = write!(
+                        svg,
+                        "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"2\"/>",
+                        x, y
+                    );
+                }
+                svg.push_str("</g>");
+            }
+        }
+    }
+
+    svg.push_str("</svg>");
+
+    Ok(svg)
+}
+
+/// RGB pixels of an image.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0xff; width as usize * height as usize * 3],
+        }
+    }
+}
+
+impl Surface for Canvas {
+    fn set(&mut self, x: i64, y: i64, color: [u8; 3]) {
+        if x >= 0
+            && y >= 0
+            && x < i64::from(self.width)
+            && y < i64::from(self.height)
+        {
+            let index = (y as usize * self.width as usize + x as usize) * 3;
+            self.pixels[index..index + 3].copy_from_slice(&color);
+        }
+    }
+}
+
+/// Render the fields of the storage as a PNG image.
+pub fn render_png(
+    storage: &Storage,
+    settings: &RenderSettings,
+) -> Result<Vec<u8>, RenderError> {
+    let chart = Chart::new(storage, settings, &Area::of_image(settings)?)?;
+    let mut canvas = Canvas::new(settings.width, settings.height);
+
+    let left = PADDING_LEFT;
+    let top = PADDING_TOP;
+    let right = f64::from(settings.width) - PADDING_RIGHT;
+    let bottom = f64::from(settings.height) - PADDING_BOTTOM;
+    canvas.line((left, top), (right, top), FRAME_COLOR);
+    canvas.line((right, top), (right, bottom), FRAME_COLOR);
+    canvas.line((right, bottom), (left, bottom), FRAME_COLOR);
+    canvas.line((left, bottom), (left, top), FRAME_COLOR);
+
+    canvas.series(&chart, settings.mark, 1);
+
+    let mut bytes = vec![];
+    {
+        let mut encoder = Encoder::new(&mut bytes, canvas.width, canvas.height);
+        encoder.set_color(ColorType::Rgb);
+        encoder.set_depth(BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&canvas.pixels))
+            .map_err(RenderError::Png)?;
+    }
+
+    Ok(bytes)
+}
+
+/// Render the fields of the storage in the format.
+pub fn render(
+    storage: &Storage,
+    settings: &RenderSettings,
+    format: ImageFormat,
+) -> Result<Vec<u8>, RenderError> {
+    match format {
+        ImageFormat::Svg =>
+            render_svg(storage, settings).map(String::into_bytes),
+        ImageFormat::Png => render_png(storage, settings),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::{parse::record::Record, storage::Number};
+
+    fn storage(records: &[&[(&'static str, Number)]]) -> Storage {
+        let mut storage = Storage::new();
+
+        for record in records {
+            let record = Record(
+                record
+                    .iter()
+                    .map(|(field, number)| (Cow::Borrowed(*field), *number))
+                    .collect(),
+            );
+            storage.push_record(&record).unwrap();
+        }
+
+        storage
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let storage = storage(&[
+            &[("t", Number::Int(0)), ("a", Number::Float(1.0))],
+            &[("t", Number::Int(1)), ("a", Number::Float(f64::NAN))],
+            &[("t", Number::Int(2)), ("a", Number::Float(3.0))],
+            &[("t", Number::Int(3)), ("a", Number::Float(2.0))],
+        ]);
+        let settings = RenderSettings {
+            x: Some("t".to_string()),
+            width: 200,
+            height: 100,
+            ..RenderSettings::default()
+        };
+
+        let svg = render_svg(&storage, &settings).unwrap();
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>"));
+        // The missing value splits the line in two.
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains(">a</text>"));
+
+        let settings = RenderSettings {
+            mark: Mark::Scatter,
+            ..settings
+        };
+        let svg = render_svg(&storage, &settings).unwrap();
+
+        assert_eq!(svg.matches("<circle").count(), 3);
+    }
+
+    #[test]
+    fn test_render_png() {
+        let storage = storage(&[
+            &[("a", Number::Int(1)), ("b", Number::Float(1.0))],
+            &[("a", Number::Int(2)), ("b", Number::Float(-1.0))],
+        ]);
+        let settings = RenderSettings {
+            width: 120,
+            height: 80,
+            ..RenderSettings::default()
+        };
+
+        let png = render_png(&storage, &settings).unwrap();
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // The width and the height in the IHDR chunk.
+        assert_eq!(&png[16..24], &[0, 0, 0, 120, 0, 0, 0, 80]);
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let storage = storage(&[&[("a", Number::Int(1))]]);
+        let settings = RenderSettings {
+            y: Some(vec!["b".to_string()]),
+            ..RenderSettings::default()
+        };
+
+        match render_svg(&storage, &settings) {
+            Err(RenderError::NoField(field)) => assert_eq!(field, "b"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let settings = RenderSettings {
+            width: 10,
+            ..RenderSettings::default()
+        };
+
+        match render_png(&storage, &settings) {
+            Err(RenderError::TooSmall) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let settings = RenderSettings {
+            height: MAX_IMAGE_SIZE + 1,
+            ..RenderSettings::default()
+        };
+
+        match render_png(&storage, &settings) {
+            Err(RenderError::TooLarge) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        assert_eq!(ImageFormat::from_path("out.PNG"), ImageFormat::Png);
+        assert_eq!(ImageFormat::from_path("out.svg"), ImageFormat::Svg);
+    }
+}
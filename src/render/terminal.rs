@@ -0,0 +1,200 @@
+//! Rendering with braille characters.
+
+use super::*;
+
+/// Width of the column holding the labels of the y axis.
+const LABEL_WIDTH: usize = 10;
+
+/// The bits of the dots of a braille character, by row and column.
+const DOTS: [[u8; 2]; 4] =
+    [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Cells of braille characters, each one 2 dots wide and 4 dots high.
+///
+/// A cell takes the color of the last dot drawn in it.
+struct Braille {
+    columns: usize,
+    rows: usize,
+    cells: Vec<(u8, [u8; 3])>,
+}
+
+impl Braille {
+    fn new(columns: usize, rows: usize) -> Self {
+        Self {
+            columns,
+            rows,
+            cells: vec![(0, [0; 3]); columns * rows],
+        }
+    }
+
+    /// Write the row of cells, coloring the runs of cells of the same color.
+    fn write_row(&self, row: usize, line: &mut String) {
+        let mut current = None;
+
+        for (dots, color) in &self.cells[row * self.columns..][..self.columns] {
+            if *dots == 0 {
+                line.push(' ');
+                continue;
+            }
+
+            if current != Some(*color) {
+                let [r, g, b] = color;
+                line.push_str(&format!("\x1b[38;2;{};{};{}m", r, g, b));
+                current = Some(*color);
+            }
+            line.push(std::char::from_u32(0x2800 + u32::from(*dots)).unwrap());
+        }
+
+        if current.is_some() {
+            line.push_str("\x1b[0m");
+        }
+    }
+}
+
+impl Surface for Braille {
+    fn set(&mut self, x: i64, y: i64, color: [u8; 3]) {
+        if x >= 0
+            && y >= 0
+            && (x as usize) < self.columns * 2
+            && (y as usize) < self.rows * 4
+        {
+            let (x, y) = (x as usize, y as usize);
+            let cell = &mut self.cells[y / 4 * self.columns + x / 2];
+            cell.0 |= DOTS[y % 4][x % 2];
+            cell.1 = color;
+        }
+    }
+}
+
+/// Write the text over the line at the position, measured in characters.
+fn overwrite(line: &mut [char], position: usize, text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        if let Some(slot) = line.get_mut(position + i) {
+            *slot = c;
+        }
+    }
+}
+
+/// Render the fields of the storage as lines of braille characters, colored
+/// with ANSI escape codes.
+///
+/// The width and the height in the settings are measured in characters.
+/// The first line holds the legend and the last one the range of the x axis.
+pub fn render_terminal(
+    storage: &Storage,
+    settings: &RenderSettings,
+) -> Result<String, RenderError> {
+    let columns = settings.width as usize;
+    let rows = settings.height as usize;
+    if columns <= LABEL_WIDTH || rows <= 2 {
+        return Err(RenderError::TooSmall);
+    }
+
+    let plot_columns = columns - LABEL_WIDTH;
+    let plot_rows = rows - 2;
+    let area = Area {
+        left: 0.0,
+        top: 0.0,
+        width: (plot_columns * 2 - 1) as f64,
+        height: (plot_rows * 4 - 1) as f64,
+    };
+    let chart = Chart::new(storage, settings, &area)?;
+
+    let mut braille = Braille::new(plot_columns, plot_rows);
+    braille.series(&chart, settings.mark, 0);
+
+    let mut lines = vec![];
+
+    let mut legend = String::new();
+    for series in &chart.series {
+        let [r, g, b] = series.color;
+        legend.push_str(&format!(
+            "\x1b[38;2;{};{};{}m{}\x1b[0m  ",
+            r, g, b, series.name
+        ));
+    }
+    lines.push(legend.trim_end().to_string());
+
+    for row in 0..plot_rows {
+        let label = if row == 0 {
+            format_number(chart.y_range.1)
+        } else if row == plot_rows - 1 {
+            format_number(chart.y_range.0)
+        } else {
+            String::new()
+        };
+
+        let mut line = format!("{:>1$} ", label, LABEL_WIDTH - 1);
+        braille.write_row(row, &mut line);
+        lines.push(line);
+    }
+
+    let min = format_number(chart.x_range.0);
+    let max = format_number(chart.x_range.1);
+    let mut axis = vec![' '; columns];
+    overwrite(
+        &mut axis,
+        LABEL_WIDTH
+            + (plot_columns.saturating_sub(chart.x_name.chars().count())) / 2,
+        &chart.x_name,
+    );
+    overwrite(&mut axis, LABEL_WIDTH, &min);
+    overwrite(&mut axis, columns.saturating_sub(max.len()), &max);
+    lines.push(axis.into_iter().collect::<String>().trim_end().to_string());
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::{parse::record::Record, storage::Number};
+
+    #[test]
+    fn test_render_terminal() {
+        let mut storage = Storage::new();
+        for (t, a) in &[(0, 0.0), (1, 1.0), (2, 0.5)] {
+            let record = Record(
+                vec![
+                    (Cow::Borrowed("t"), Number::Int(*t)),
+                    (Cow::Borrowed("a"), Number::Float(*a)),
+                ]
+                .into_iter()
+                .collect(),
+            );
+            storage.push_record(&record).unwrap();
+        }
+        let settings = RenderSettings {
+            x: Some("t".to_string()),
+            width: 20,
+            height: 6,
+            ..RenderSettings::default()
+        };
+
+        let text = render_terminal(&storage, &settings).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].contains("a"));
+        assert!(lines[1].starts_with("        1 "));
+        assert!(lines[4].starts_with("        0 "));
+        assert_eq!(lines[5], "          0   t    2");
+        assert!(lines[1..5].iter().any(|line| line
+            .chars()
+            .any(|c| c > '\u{2800}' && c <= '\u{28ff}')));
+
+        let settings = RenderSettings {
+            height: 2,
+            ..settings
+        };
+
+        match render_terminal(&storage, &settings) {
+            Err(RenderError::TooSmall) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
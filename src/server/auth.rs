@@ -0,0 +1,234 @@
+//! Token authentication.
+
+use ::actix_web::{
+    http::header::{self, HeaderValue},
+    middleware::{Middleware, Response, Started},
+    HttpRequest,
+    HttpResponse,
+    Result,
+};
+
+/// The name of the query parameter holding the token.
+const QUERY_PARAM: &str = "token";
+
+/// The name of the cookie holding the token.
+const COOKIE_NAME: &str = "plotka_token";
+
+/// Middleware rejecting the requests which do not carry the token with
+/// `401 Unauthorized`.
+///
+/// The token is accepted in the `token` query parameter, in the
+/// `Authorization: Bearer <token>` header or in a cookie. The responses to
+/// the requests authorized with the query parameter set the cookie, so that
+/// a browser opening `/?token=<token>` can load the client and connect over
+/// WS without passing the token again.
+#[derive(Clone)]
+pub struct TokenAuth {
+    token: String,
+    secure: bool,
+}
+
+impl TokenAuth {
+    /// Construct the middleware. Returns `None` if the token is empty or
+    /// contains characters which are not allowed unescaped in URLs
+    /// (anything but letters, digits and `-._~`).
+    ///
+    /// If `secure` is set, i.e. the server is served over TLS, the cookie
+    /// is only sent over HTTPS.
+    pub fn new(token: &str, secure: bool) -> Option<Self> {
+        let is_valid = !token.is_empty()
+            && token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c));
+
+        if is_valid {
+            Some(Self {
+                token: token.to_string(),
+                secure,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn matches(&self, token: &str) -> bool {
+        tokens_match(&self.token, token)
+    }
+
+    fn matches_query<S>(&self, req: &HttpRequest<S>) -> bool {
+        req.query()
+            .get(QUERY_PARAM)
+            .is_some_and(|token| self.matches(token))
+    }
+}
+
+impl<S> Middleware<S> for TokenAuth {
+    fn start(&self, req: &HttpRequest<S>) -> Result<Started> {
+        let header = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(bearer_token);
+        let cookie = req.cookie(COOKIE_NAME);
+
+        if self.matches_query(req)
+            || header.is_some_and(|token| self.matches(token))
+            || cookie.is_some_and(|cookie| self.matches(cookie.value()))
+        {
+            Ok(Started::Done)
+        } else {
+            Ok(Started::Response(
+                HttpResponse::Unauthorized()
+                    .header(header::WWW_AUTHENTICATE, "Bearer")
+                    .finish(),
+            ))
+        }
+    }
+
+    fn response(
+        &self,
+        req: &HttpRequest<S>,
+        mut resp: HttpResponse,
+    ) -> Result<Response> {
+        if self.matches_query(req) {
+            // The token consists of URL-safe characters only, so it is also
+            // a valid cookie value.
+            let cookie = format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Strict{}",
+                COOKIE_NAME,
+                self.token,
+                if self.secure { "; Secure" } else { "" }
+            );
+
+            if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+                resp.headers_mut().append(header::SET_COOKIE, cookie);
+            }
+        }
+
+        Ok(Response::Done(resp))
+    }
+}
+
+/// Get the token from the value of the `Authorization` header.
+fn bearer_token(value: &str) -> Option<&str> {
+    let mut parts = value.splitn(2, ' ');
+
+    match (parts.next(), parts.next()) {
+        (Some(scheme), Some(token))
+            if scheme.eq_ignore_ascii_case("bearer") =>
+            Some(token.trim()),
+        _ => None,
+    }
+}
+
+/// Compare the tokens in time independent of the position of the first
+/// difference.
+fn tokens_match(expected: &str, token: &str) -> bool {
+    expected.len() == token.len()
+        && expected
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod test {
+    use ::actix_web::{http::StatusCode, test::TestServer, HttpMessage};
+    use ::pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn start_server(secure: bool) -> TestServer {
+        TestServer::new(move |app| {
+            app.middleware(TokenAuth::new("s3cr3t", secure).unwrap())
+                .handler(|_| HttpResponse::Ok().finish())
+        })
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(TokenAuth::new("s3cr3t-token_1.0~", false).is_some());
+        assert!(TokenAuth::new("", false).is_none());
+        assert!(TokenAuth::new("with space", false).is_none());
+        assert!(TokenAuth::new("a;b", false).is_none());
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("bearer abc "), Some("abc"));
+        assert_eq!(bearer_token("Basic abc"), None);
+        assert_eq!(bearer_token("Bearer"), None);
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("abc", "abc"));
+        assert!(!tokens_match("abc", "abd"));
+        assert!(!tokens_match("abc", "ab"));
+        assert!(!tokens_match("abc", ""));
+    }
+
+    #[test]
+    fn test_requests() {
+        let mut srv = start_server(false);
+
+        let request = srv.get().finish().unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer"
+        );
+
+        let request = srv.get().uri(srv.url("/?token=wrong")).finish().unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request =
+            srv.get().uri(srv.url("/?token=s3cr3t")).finish().unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = response.headers().get(header::SET_COOKIE).unwrap();
+        assert!(cookie.to_str().unwrap().starts_with("plotka_token=s3cr3t;"));
+        assert!(!cookie.to_str().unwrap().contains("Secure"));
+
+        let request = srv
+            .get()
+            .header(header::AUTHORIZATION, "Bearer s3cr3t")
+            .finish()
+            .unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+
+        let request = srv
+            .get()
+            .header(header::AUTHORIZATION, "Bearer wrong")
+            .finish()
+            .unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = srv
+            .get()
+            .header(header::COOKIE, "plotka_token=s3cr3t")
+            .finish()
+            .unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_secure_cookie() {
+        let mut srv = start_server(true);
+
+        let request =
+            srv.get().uri(srv.url("/?token=s3cr3t")).finish().unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = response.headers().get(header::SET_COOKIE).unwrap();
+        assert!(cookie.to_str().unwrap().ends_with("; Secure"));
+    }
+}
@@ -0,0 +1,37 @@
+//! The built-in web client, served when no static path is given.
+
+use ::actix_web::*;
+
+use super::*;
+
+/// The page of the built-in client.
+const CLIENT_INDEX: &str = include_str!("../../client/index.html");
+
+/// The script of the built-in client.
+const CLIENT_SCRIPT: &str = include_str!("../../client/plotka.js");
+
+/// Respond with the page of the built-in client.
+pub fn get_client_index<R, P>(
+    _req: &HttpRequest<WsSessionState<R, P>>,
+) -> HttpResponse
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(CLIENT_INDEX)
+}
+
+/// Respond with the script of the built-in client.
+pub fn get_client_script<R, P>(
+    _req: &HttpRequest<WsSessionState<R, P>>,
+) -> HttpResponse
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    HttpResponse::Ok()
+        .content_type("application/javascript; charset=utf-8")
+        .body(CLIENT_SCRIPT)
+}
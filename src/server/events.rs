@@ -0,0 +1,114 @@
+//! Server-sent events.
+
+use ::actix::*;
+use ::actix_web::*;
+
+use std::{io, marker::PhantomData, time::Duration};
+
+use super::*;
+
+/// How often a comment is sent to keep idle connections open.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A session streaming the messages as
+/// [server-sent events](https://html.spec.whatwg.org/multipage/server-sent-events.html).
+///
+/// It connects to the server just like a WS session. Every message is sent
+/// as the data of a single event.
+pub struct SseSession<R, P>
+where
+    R: 'static + io::Read,
+    P: Parser<R>,
+{
+    id: usize,
+    since: Option<u64>,
+    instance: Option<String>,
+    parser: PhantomData<(R, P)>,
+}
+
+impl<R, P> Actor for SseSession<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Context = HttpContext<Self, WsSessionState<R, P>>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let addr = ctx.address();
+
+        ctx.state()
+            .addr
+            .send(Connect {
+                addr: addr.recipient(),
+                since: self.since,
+                instance: self.instance.clone(),
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(res) => act.id = res,
+                    _ => ctx.stop(),
+                }
+
+                fut::ok(())
+            })
+            .wait(ctx);
+
+        ctx.run_interval(KEEP_ALIVE_INTERVAL, |_, ctx| {
+            ctx.write(": keep-alive\n\n")
+        });
+    }
+
+    fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
+        ctx.state().addr.do_send(Disconnect { id: self.id });
+
+        Running::Stop
+    }
+}
+
+impl<R, P> Handler<WsMessage> for SseSession<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = ();
+
+    fn handle(&mut self, msg: WsMessage, ctx: &mut Self::Context) {
+        match msg {
+            // The messages are serialized without newlines, so each one fits
+            // in a single `data` field.
+            WsMessage::Text(text) => ctx.write(format!("data: {}\n\n", text)),
+            WsMessage::Close => {
+                ctx.write_eof();
+                ctx.stop();
+            }
+        }
+    }
+}
+
+/// Stream the messages sent to the clients as server-sent events.
+///
+/// Just like [`ws_handshake`], accepts the `since` and `instance` query
+/// parameters.
+pub fn get_events<R, P>(
+    req: &HttpRequest<WsSessionState<R, P>>,
+) -> Result<HttpResponse, actix_web::Error>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    let since = query_since(req)?;
+    let session = SseSession {
+        id: 0,
+        since,
+        instance: query_instance(req),
+        parser: PhantomData,
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header(http::header::CACHE_CONTROL, "no-cache")
+        // Keep proxies such as nginx from buffering the stream.
+        .header("X-Accel-Buffering", "no")
+        .body(HttpContext::create(req.clone(), session)))
+}
@@ -1,29 +1,88 @@
 //! Server.
 
-use ::actix::*;
+use ::actix::{actors::signal, *};
 use ::actix_web::*;
+use ::futures::{future, Future};
 use ::log::info;
 use ::rand::prelude::*;
-use ::serde_json::Value;
+use ::serde_json::{json, Value};
 
-use std::{collections::HashMap, io, marker::PhantomData, sync::mpsc::Sender};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs,
+    io,
+    marker::PhantomData,
+    mem,
+    path::PathBuf,
+    sync::mpsc::Sender,
+    time::Duration,
+};
 
 use crate::{
-    compose::{compose_init_message, compose_push_record_message},
-    parse::{record::Record, Parser},
-    storage::{Storage, StorageError},
+    compose::{
+        compose_init_chunk_message,
+        compose_init_done_message,
+        compose_push_record_message,
+        compose_server_info_message,
+        compose_server_shutdown_message,
+        compose_stream_ended_message,
+    },
+    export::export_arrow,
+    input::{decompress, Compression, Source},
+    parse::{record::Record, Columns, Parser},
+    render::{
+        render,
+        render_terminal,
+        ImageFormat,
+        Mark,
+        RenderError,
+        RenderSettings,
+        MAX_IMAGE_SIZE,
+    },
+    storage::{Labels, Storage, StorageError},
+    transform::Transform,
 };
 
+mod auth;
+mod client;
+mod events;
+mod request;
 mod session;
+mod terminal;
 
 use self::session::*;
 
-pub use self::session::WsSessionState;
+pub use self::{
+    auth::TokenAuth,
+    client::{get_client_index, get_client_script},
+    events::get_events,
+    session::WsSessionState,
+    terminal::{TerminalSession, TerminalView},
+};
+
+/// Maximal size of a request body accepted by [`post_records`].
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// Maximal number of values in an `initStorageChunk` message.
+const INIT_CHUNK_VALUES: usize = 16 * 1024;
+
+/// How long the clients are given to receive the last messages before the
+/// server stops.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_millis(200);
+
+/// The name of the dataset used by sources which have not been given one.
+pub const DEFAULT_DATASET: &str = "default";
 
 #[derive(Message)]
 #[rtype(usize)]
 struct Connect {
     addr: Recipient<WsMessage>,
+    /// The sequence number of the last record received by a reconnecting
+    /// client.
+    since: Option<u64>,
+    /// The instance the client has received the record from.
+    instance: Option<String>,
 }
 
 #[derive(Message)]
@@ -31,14 +90,119 @@ struct Disconnect {
     id: usize,
 }
 
+/// Subscribe a session to the datasets. `None` means all datasets.
+#[derive(Message)]
+struct Subscribe {
+    id: usize,
+    datasets: Option<Vec<String>>,
+}
+
+/// Send a session the records it has missed.
+#[derive(Message)]
+struct Resume {
+    id: usize,
+    since: u64,
+    instance: Option<String>,
+}
+
+/// A message to a client.
 #[derive(Message, Clone)]
-struct WsMessage(String);
+enum WsMessage {
+    /// Send the text.
+    Text(String),
+    /// Close the connection.
+    Close,
+}
 
 /// A wrapper around the parser input.
+///
+/// Invalid inputs are counted as rejected and reported to the clients once
+/// all the inputs have ended.
+#[derive(Message)]
+pub struct InputMessage<I>
+where
+    I: Send,
+{
+    /// The dataset the input belongs to.
+    pub dataset: String,
+    /// The input.
+    pub input: I,
+}
+
+/// A record which could not be read from a source, counted as rejected.
+#[derive(Message)]
+pub struct RejectedInput;
+
+/// A batch of parser inputs received over HTTP.
+///
+/// Invalid inputs are counted in the returned [`IngestReport`].
 #[derive(Message)]
-pub struct InputMessage<I>(pub I)
+#[rtype(IngestReport)]
+pub struct IngestMessage<I>
 where
-    I: Send;
+    I: Send,
+{
+    /// The dataset the inputs belong to.
+    pub dataset: String,
+    /// The inputs.
+    pub inputs: Vec<I>,
+}
+
+/// The number of records accepted, filtered out and rejected by the server.
+#[derive(Debug, Default)]
+pub struct IngestReport {
+    /// Records parsed and pushed to the storage.
+    pub accepted: usize,
+    /// Records dropped by the filter.
+    pub filtered: usize,
+    /// Records which could not be read, parsed or pushed to the storage.
+    pub rejected: usize,
+}
+
+/// Export a dataset as an Arrow IPC stream.
+pub(crate) struct ExportArrow {
+    pub(crate) dataset: String,
+}
+
+impl Message for ExportArrow {
+    type Result = Result<Vec<u8>, InternalError>;
+}
+
+/// Render a dataset as an image.
+struct Render {
+    dataset: String,
+    settings: RenderSettings,
+    format: ImageFormat,
+}
+
+impl Message for Render {
+    type Result = Result<Vec<u8>, InternalError>;
+}
+
+/// Render a dataset with braille characters.
+struct RenderTerminal {
+    dataset: String,
+    settings: RenderSettings,
+}
+
+impl Message for RenderTerminal {
+    type Result = Result<String, InternalError>;
+}
+
+/// A message sent once all the input sources have ended.
+#[derive(Message)]
+pub struct EndOfInput;
+
+/// A dataset rendered to a file when the server stops.
+pub struct RenderTarget {
+    /// The file the image is written to. The format is chosen by its
+    /// extension.
+    pub path: PathBuf,
+    /// The rendered dataset.
+    pub dataset: String,
+    /// Render settings.
+    pub settings: RenderSettings,
+}
 
 /// A message to stop other threads.
 pub struct StopAppMessage;
@@ -48,6 +212,14 @@ pub struct StopAppMessage;
 pub enum InternalError {
     Parse,
     Storage(StorageError),
+    /// The record could not be transformed.
+    Transform,
+    /// The requested dataset does not exist.
+    NoDataset,
+    /// The dataset could not be exported.
+    Export,
+    /// The dataset could not be rendered.
+    Render(RenderError),
 }
 
 impl StopAppMessage {
@@ -56,18 +228,48 @@ impl StopAppMessage {
     }
 }
 
+/// A connected client.
+struct Subscriber {
+    addr: Recipient<WsMessage>,
+    /// Datasets the client is subscribed to. `None` means all datasets.
+    datasets: Option<HashSet<String>>,
+}
+
+impl Subscriber {
+    fn is_subscribed(&self, dataset: &str) -> bool {
+        self.datasets
+            .as_ref()
+            .is_none_or(|datasets| datasets.contains(dataset))
+    }
+}
+
 /// Internal server.
 pub struct Server<R, P>
 where
     R: 'static + io::Read,
     P: Parser<R>,
 {
-    sessions: HashMap<usize, Recipient<WsMessage>>,
-    storage: Storage,
+    sessions: HashMap<usize, Subscriber>,
+    storages: HashMap<String, Storage>,
     rng: SmallRng,
     stop_tx: Sender<StopAppMessage>,
     reader: PhantomData<R>,
     parser: P,
+    transform: Transform,
+    render_on_exit: Option<RenderTarget>,
+    exit_on_eof: bool,
+    /// The number of records accepted, filtered out and rejected so far.
+    totals: IngestReport,
+    /// Whether all the input sources have ended.
+    has_ended: bool,
+    /// Whether the clients have been told the server is shutting down.
+    is_shutting_down: bool,
+    /// The sequence number of the next stored record.
+    next_seq: u64,
+    /// A random id of this run of the server. Sequence numbers start from 0
+    /// in every run, so a client resuming with a sequence number received
+    /// from another instance gets the init messages instead.
+    instance: String,
 }
 
 impl<R, P> Server<R, P>
@@ -75,41 +277,447 @@ where
     R: io::Read,
     P: Parser<R>,
 {
-    /// Create a new server.
-    pub fn new(stop_tx: Sender<StopAppMessage>, parser: P) -> Self {
+    /// Create a new server with empty storages for the datasets.
+    ///
+    /// Storages of other datasets are created when their first record is
+    /// received. The transform is applied to every record before it is
+    /// stored.
+    ///
+    /// Once all the inputs have ended, the clients are notified and the
+    /// server keeps serving the datasets, unless `exit_on_eof` is set.
+    /// If a render target is given, the dataset is rendered to the file when
+    /// the server stops.
+    pub fn new(
+        stop_tx: Sender<StopAppMessage>,
+        parser: P,
+        datasets: Vec<String>,
+        transform: Transform,
+        render_on_exit: Option<RenderTarget>,
+        exit_on_eof: bool,
+    ) -> Self {
+        let mut rng = SmallRng::from_entropy();
+        let instance = format!("{:016x}", rng.gen::<u64>());
+
         Server {
             sessions: HashMap::new(),
-            rng: SmallRng::from_entropy(),
-            storage: Storage::new(),
+            rng,
+            storages: datasets
+                .into_iter()
+                .map(|dataset| (dataset, Storage::new()))
+                .collect(),
             stop_tx,
             reader: PhantomData,
             parser,
+            transform,
+            render_on_exit,
+            exit_on_eof,
+            totals: IngestReport::default(),
+            has_ended: false,
+            is_shutting_down: false,
+            next_seq: 0,
+            instance,
+        }
+    }
+
+    fn render(
+        &self,
+        dataset: &str,
+        settings: &RenderSettings,
+        format: ImageFormat,
+    ) -> Result<Vec<u8>, InternalError> {
+        let storage =
+            self.storages.get(dataset).ok_or(InternalError::NoDataset)?;
+
+        render(storage, settings, format).map_err(InternalError::Render)
+    }
+
+    /// Notify the clients, close their connections and stop the server once
+    /// they have received the last messages.
+    fn shut_down(&mut self, ctx: &mut Context<Self>) {
+        if self.is_shutting_down {
+            return;
+        }
+
+        info!("Shutting down...");
+        self.is_shutting_down = true;
+
+        let message =
+            WsMessage::Text(compose_server_shutdown_message().to_string());
+        for session in self.sessions.values() {
+            let _ = session.addr.do_send(message.clone());
+            let _ = session.addr.do_send(WsMessage::Close);
         }
+
+        ctx.run_later(SHUTDOWN_GRACE_PERIOD, |_, ctx| ctx.stop());
     }
 
-    fn broadcast_ws_message(&self, message: &Value) {
+    fn broadcast_ws_message(&self, dataset: &str, message: &Value) {
         info!("Sending a WS message.");
 
-        let message = WsMessage(message.to_string());
+        let message = WsMessage::Text(message.to_string());
+
+        for session in self.sessions.values() {
+            if session.is_subscribed(dataset) {
+                let _ = session.addr.do_send(message.clone());
+            }
+        }
+    }
+
+    fn send_init_messages(&self, session: &Subscriber) {
+        for (dataset, storage) in &self.storages {
+            if !session.is_subscribed(dataset) {
+                continue;
+            }
+
+            // Bound the size of the messages no matter how many fields
+            // the records have.
+            let fields = storage.columns().count().max(1);
+            for chunk in storage.chunks((INIT_CHUNK_VALUES / fields).max(1)) {
+                match compose_init_chunk_message(dataset, &chunk) {
+                    Ok(message) => {
+                        let _ = session.addr.do_send(WsMessage::Text(message));
+                    }
+                    Err(_) => info!("Could not compose a chunk."),
+                }
+            }
+
+            let message = compose_init_done_message(dataset, storage);
+            let _ = session.addr.do_send(WsMessage::Text(message.to_string()));
+        }
+
+        self.send_stream_ended_message(session);
+    }
+
+    /// Check whether a client can resume after the record with the sequence
+    /// number received from the instance, i.e. whether all the newer records
+    /// are still stored.
+    fn can_resume(&self, since: u64, instance: Option<&str>) -> bool {
+        let first_seq =
+            self.storages.values().filter_map(Storage::first_seq).min();
 
-        for session_addr in self.sessions.values() {
-            let _ = session_addr.do_send(message.clone());
+        instance == Some(self.instance.as_str())
+            && since < self.next_seq
+            && first_seq.is_none_or(|first_seq| since + 1 >= first_seq)
+    }
+
+    /// Send the session the records with sequence numbers greater than
+    /// `since`.
+    ///
+    /// Falls back to the init messages if the client cannot resume, e.g.
+    /// because it has been connected to another instance.
+    fn send_missed_records(
+        &self,
+        session: &Subscriber,
+        since: u64,
+        instance: Option<&str>,
+    ) {
+        if !self.can_resume(since, instance) {
+            return self.send_init_messages(session);
+        }
+
+        for (dataset, storage) in &self.storages {
+            if session.is_subscribed(dataset) {
+                for (seq, record) in storage.records_since(since) {
+                    let labels = Some(seq)
+                        .filter(|seq| Some(*seq) == storage.first_seq())
+                        .map(|_| storage.labels());
+                    let message = compose_push_record_message(
+                        dataset, &record, seq, labels,
+                    );
+                    let _ = session
+                        .addr
+                        .do_send(WsMessage::Text(message.to_string()));
+                }
+            }
+        }
+
+        self.send_stream_ended_message(session);
+    }
+
+    /// Let late clients know there will be no more records.
+    fn send_stream_ended_message(&self, session: &Subscriber) {
+        if self.has_ended {
+            let message =
+                compose_stream_ended_message(&self.totals, &self.storages);
+            let _ = session.addr.do_send(WsMessage::Text(message.to_string()));
+        }
+    }
+
+    /// Parse the input, store its records and count them in the report.
+    ///
+    /// The update messages of the stored records are sent to the clients.
+    fn ingest(
+        &mut self,
+        dataset: &str,
+        input: &P::Input,
+        report: &mut IngestReport,
+    ) {
+        match self.parser.parse_columns(input) {
+            None => {
+                let result = self.handle_input(dataset, input);
+                self.count_result(result, report);
+            }
+            Some(Ok(columns)) =>
+                self.handle_columns(dataset, input, columns, report),
+            Some(Err(_)) => {
+                info!("An error occured.");
+                report.rejected += 1;
+            }
+        }
+    }
+
+    /// Count the record in the report and send the update messages of the
+    /// stored records.
+    fn count_result(
+        &self,
+        result: Result<Vec<(String, Value)>, InternalError>,
+        report: &mut IngestReport,
+    ) {
+        match result {
+            Err(_) => {
+                // TODO error message? error kind?
+                info!("An error occured.");
+                report.rejected += 1;
+            }
+            Ok(ref messages) if messages.is_empty() => report.filtered += 1,
+            Ok(messages) => {
+                for (dataset, message) in messages {
+                    // Send update message.
+                    self.broadcast_ws_message(&dataset, &message);
+                }
+
+                report.accepted += 1;
+            }
         }
     }
 
-    fn handle_input<'a>(
-        &'a mut self,
-        input: &'a P::Input,
-    ) -> Result<Record<'a>, InternalError> {
+    /// Parse and transform the input and push the record to the storage of
+    /// its dataset.
+    ///
+    /// The dataset is either the one chosen by the parser or the given one.
+    /// Returns the update messages of the stored records along with their
+    /// datasets. These are the record itself (unless it has been filtered
+    /// out) and the resampled record, once a time bucket is complete.
+    fn handle_input(
+        &mut self,
+        dataset: &str,
+        input: &P::Input,
+    ) -> Result<Vec<(String, Value)>, InternalError> {
         let record =
             self.parser.parse(input).map_err(|_| InternalError::Parse)?;
 
-        self.storage
-            .push_record(&record)
-            .map_err(|e| InternalError::Storage(e))?;
+        // E.g. a logfmt line without numeric values. There is nothing to
+        // store, so it is treated like a record dropped by the filter.
+        if record.0.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let dataset = self
+            .parser
+            .dataset(input)
+            .map_err(|_| InternalError::Parse)?
+            .map_or_else(|| dataset.to_string(), Cow::into_owned);
+
+        let labels = if self.storages.contains_key(&dataset) {
+            None
+        } else {
+            Some(self.input_labels(input)?)
+        };
+
+        transform_and_store(
+            &mut self.transform,
+            &mut self.storages,
+            &mut self.next_seq,
+            dataset,
+            record,
+            labels,
+            P::VARIABLE_FIELDS,
+        )
+    }
+
+    /// Store the records parsed at once and count them in the report.
+    ///
+    /// Unless there are any transformations to apply, the columns are
+    /// appended to the storage of the dataset as a whole. Otherwise every
+    /// record is transformed and stored on its own.
+    fn handle_columns(
+        &mut self,
+        dataset: &str,
+        input: &P::Input,
+        columns: Columns,
+        report: &mut IngestReport,
+    ) {
+        report.rejected += columns.rejected;
+
+        let dataset_and_labels = self
+            .parser
+            .dataset(input)
+            .map_err(|_| InternalError::Parse)
+            .map(|name| {
+                name.map_or_else(|| dataset.to_string(), Cow::into_owned)
+            })
+            .and_then(|dataset| {
+                if self.storages.contains_key(&dataset) {
+                    Ok((dataset, None))
+                } else {
+                    Ok((dataset, Some(self.input_labels(input)?)))
+                }
+            });
+        let (dataset, mut labels) = match dataset_and_labels {
+            Ok(dataset_and_labels) => dataset_and_labels,
+            Err(_) => {
+                info!("An error occured.");
+                report.rejected += columns.len;
+
+                return;
+            }
+        };
+
+        // E.g. a record batch without numeric columns, just like an empty
+        // record.
+        if columns.columns.is_empty() || columns.len == 0 {
+            report.filtered += columns.len;
+
+            return;
+        }
+
+        if !self.transform.is_empty() {
+            for record in columns.records() {
+                let result = transform_and_store(
+                    &mut self.transform,
+                    &mut self.storages,
+                    &mut self.next_seq,
+                    dataset.clone(),
+                    record,
+                    labels.take(),
+                    P::VARIABLE_FIELDS,
+                );
+                self.count_result(result, report);
+            }
+
+            return;
+        }
+
+        let seq = self.next_seq;
+        let storage =
+            self.storages.entry(dataset.clone()).or_insert_with(|| {
+                Storage::with_labels(labels.unwrap_or_default())
+            });
+        let first_index = storage.records_len();
+        // Only the first record of a dataset carries its labels.
+        let first_seq = storage.first_seq().unwrap_or(seq);
+
+        if storage.push_columns_with_seq(columns.columns, seq).is_err() {
+            info!("An error occured.");
+            report.rejected += columns.len;
+
+            return;
+        }
+        self.next_seq += columns.len as u64;
+        report.accepted += columns.len;
+
+        let storage = &self.storages[&dataset];
+        for (seq, record) in storage.records_from(first_index) {
+            let labels = Some(storage.labels()).filter(|_| seq == first_seq);
+            let message =
+                compose_push_record_message(&dataset, &record, seq, labels);
+
+            self.broadcast_ws_message(&dataset, &message);
+        }
+    }
+
+    /// Get the labels of the dataset the input belongs to.
+    fn input_labels(&self, input: &P::Input) -> Result<Labels, InternalError> {
+        self.parser
+            .labels(input)
+            .map(|labels| {
+                labels
+                    .into_iter()
+                    .map(|(name, value)| {
+                        (name.into_owned(), value.into_owned())
+                    })
+                    .collect()
+            })
+            .map_err(|_| InternalError::Parse)
+    }
+}
+
+/// Transform the record and push it to the storage of the dataset, along
+/// with the resampled record once a time bucket is complete. Returns the
+/// update messages, see [`Server::handle_input`].
+///
+/// The storage of a new dataset is created with the labels.
+fn transform_and_store(
+    transform: &mut Transform,
+    storages: &mut HashMap<String, Storage>,
+    next_seq: &mut u64,
+    dataset: String,
+    record: Record,
+    labels: Option<Labels>,
+    is_sparse: bool,
+) -> Result<Vec<(String, Value)>, InternalError> {
+    let record = match transform
+        .apply(&dataset, record)
+        .map_err(|_| InternalError::Transform)?
+    {
+        Some(record) => record,
+        None => return Ok(vec![]),
+    };
+
+    if !storages.contains_key(&dataset) {
+        storages.insert(
+            dataset.clone(),
+            Storage::with_labels(labels.unwrap_or_default()),
+        );
+    }
+
+    // Records which could not be stored do not enter the resampled dataset.
+    let message =
+        store_record(storages, next_seq, dataset, &record, is_sparse)?;
+    let resampled = transform
+        .resample(&message.0, &record)
+        .map_err(|_| InternalError::Transform)?;
+    let mut messages = vec![message];
+
+    if let Some((dataset, record)) = resampled {
+        messages.push(store_record(
+            storages, next_seq, dataset, &record, is_sparse,
+        )?);
+    }
+
+    Ok(messages)
+}
+
+/// Push the record to the storage of the dataset with the next sequence
+/// number. Returns the update message along with the dataset.
+///
+/// If `is_sparse` is set, the record does not have to carry the same fields
+/// as the stored ones, see [`Storage::push_sparse_record_with_seq`].
+///
+/// Takes the fields of [`Server`] rather than the server itself, since
+/// the record may borrow its parser.
+fn store_record(
+    storages: &mut HashMap<String, Storage>,
+    next_seq: &mut u64,
+    dataset: String,
+    record: &Record,
+    is_sparse: bool,
+) -> Result<(String, Value), InternalError> {
+    let seq = *next_seq;
 
-        Ok(record)
+    let storage = storages.entry(dataset.clone()).or_insert_with(Storage::new);
+    if is_sparse {
+        storage.push_sparse_record_with_seq(record, seq)
+    } else {
+        storage.push_record_with_seq(record, seq)
     }
+    .map_err(|e| InternalError::Storage(e))?;
+    *next_seq += 1;
+
+    let labels = Some(storage.labels()).filter(|_| storage.records_len() == 1);
+    let message = compose_push_record_message(&dataset, record, seq, labels);
+
+    Ok((dataset, message))
 }
 
 impl<R, P> Actor for Server<R, P>
@@ -119,12 +727,29 @@ where
 {
     type Context = Context<Self>;
 
+    fn started(&mut self, ctx: &mut Self::Context) {
+        // Shut down gracefully on SIGINT, SIGTERM and SIGQUIT.
+        signal::ProcessSignals::from_registry()
+            .do_send(signal::Subscribe(ctx.address().recipient()));
+    }
+
     fn stopping(&mut self, _: &mut Self::Context) -> Running {
         info!("Stopping...");
 
-        // Close the IO thread.
-        // TODO error handling?
-        self.stop_tx.send(StopAppMessage::new()).unwrap();
+        if let Some(target) = self.render_on_exit.take() {
+            let format = ImageFormat::from_path(&target.path);
+
+            match self.render(&target.dataset, &target.settings, format) {
+                Ok(bytes) =>
+                    if fs::write(&target.path, bytes).is_err() {
+                        info!("Could not write the rendered image.");
+                    },
+                Err(_) => info!("Could not render the dataset."),
+            }
+        }
+
+        // Close the IO thread. It is gone if it has been stopped already.
+        let _ = self.stop_tx.send(StopAppMessage::new());
 
         System::current().stop();
 
@@ -142,13 +767,28 @@ where
     fn handle(&mut self, msg: Connect, _: &mut Self::Context) -> Self::Result {
         info!("Someone has connected.");
 
+        let session = Subscriber {
+            addr: msg.addr,
+            datasets: None,
+        };
+
+        let message = compose_server_info_message(&self.instance);
+        let _ = session.addr.do_send(WsMessage::Text(message.to_string()));
+
+        // Send init messages, or only the missed records to a reconnecting
+        // client.
+        match msg.since {
+            Some(since) => self.send_missed_records(
+                &session,
+                since,
+                msg.instance.as_deref(),
+            ),
+            None => self.send_init_messages(&session),
+        }
+
         // Save sessions' address.
         let id = self.rng.gen::<usize>();
-        self.sessions.insert(id, msg.addr.clone());
-
-        // Send init message.
-        let message = compose_init_message(&self.storage);
-        msg.addr.do_send(WsMessage(message.to_string()));
+        self.sessions.insert(id, session);
 
         id
     }
@@ -168,6 +808,49 @@ where
     }
 }
 
+impl<R, P> Handler<Subscribe> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe, _: &mut Self::Context) {
+        info!("Someone has subscribed to datasets.");
+
+        if let Some(mut session) = self.sessions.remove(&msg.id) {
+            session.datasets =
+                msg.datasets.map(|datasets| datasets.into_iter().collect());
+
+            // Send the current state of the datasets the session has
+            // subscribed to.
+            self.send_init_messages(&session);
+
+            self.sessions.insert(msg.id, session);
+        }
+    }
+}
+
+impl<R, P> Handler<Resume> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = ();
+
+    fn handle(&mut self, msg: Resume, _: &mut Self::Context) {
+        info!("Someone has resumed.");
+
+        if let Some(session) = self.sessions.get(&msg.id) {
+            self.send_missed_records(
+                session,
+                msg.since,
+                msg.instance.as_deref(),
+            );
+        }
+    }
+}
+
 impl<R, P> Handler<InputMessage<P::Input>> for Server<R, P>
 where
     R: io::Read,
@@ -175,27 +858,386 @@ where
 {
     type Result = ();
 
-    fn handle(&mut self, msg: InputMessage<P::Input>, ctx: &mut Self::Context) {
+    fn handle(&mut self, msg: InputMessage<P::Input>, _: &mut Self::Context) {
         info!("Input received.");
 
-        match self.handle_input(&msg.0) {
-            Err(_) => {
-                // TODO error message? error kind?
-                info!("An error occured.");
+        // Just like in `IngestMessage`, a single invalid record does not
+        // stop the server.
+        let mut totals = mem::take(&mut self.totals);
+        self.ingest(&msg.dataset, &msg.input, &mut totals);
+        self.totals = totals;
+    }
+}
+
+impl<R, P> Handler<RejectedInput> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = ();
+
+    fn handle(&mut self, _: RejectedInput, _: &mut Self::Context) {
+        self.totals.rejected += 1;
+    }
+}
+
+impl<R, P> Handler<IngestMessage<P::Input>> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = MessageResult<IngestMessage<P::Input>>;
+
+    fn handle(
+        &mut self,
+        msg: IngestMessage<P::Input>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        info!("Input batch received.");
+
+        let mut report = IngestReport::default();
 
-                // TODO error handling?
-                self.stop_tx.send(StopAppMessage {}).unwrap();
-                ctx.stop();
+        for input in &msg.inputs {
+            self.ingest(&msg.dataset, input, &mut report);
+        }
+
+        self.totals.accepted += report.accepted;
+        self.totals.filtered += report.filtered;
+        self.totals.rejected += report.rejected;
+
+        MessageResult(report)
+    }
+}
+
+impl<R, P> Handler<ExportArrow> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = MessageResult<ExportArrow>;
+
+    fn handle(
+        &mut self,
+        msg: ExportArrow,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        info!("Exporting a dataset.");
+
+        let result = match self.storages.get(&msg.dataset) {
+            Some(storage) =>
+                export_arrow(storage).map_err(|_| InternalError::Export),
+            None => Err(InternalError::NoDataset),
+        };
+
+        MessageResult(result)
+    }
+}
+
+impl<R, P> Handler<Render> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = MessageResult<Render>;
+
+    fn handle(&mut self, msg: Render, _: &mut Self::Context) -> Self::Result {
+        info!("Rendering a dataset.");
+
+        MessageResult(self.render(&msg.dataset, &msg.settings, msg.format))
+    }
+}
+
+impl<R, P> Handler<RenderTerminal> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = MessageResult<RenderTerminal>;
+
+    fn handle(
+        &mut self,
+        msg: RenderTerminal,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let result = match self.storages.get(&msg.dataset) {
+            Some(storage) => render_terminal(storage, &msg.settings)
+                .map_err(InternalError::Render),
+            None => Err(InternalError::NoDataset),
+        };
+
+        MessageResult(result)
+    }
+}
+
+impl<R, P> Handler<EndOfInput> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = ();
+
+    fn handle(&mut self, _: EndOfInput, ctx: &mut Self::Context) {
+        info!("All inputs have ended.");
+
+        // No more records will complete the last time buckets.
+        for (dataset, record) in self.transform.flush_resampled() {
+            match store_record(
+                &mut self.storages,
+                &mut self.next_seq,
+                dataset,
+                &record,
+                P::VARIABLE_FIELDS,
+            ) {
+                Ok((dataset, message)) =>
+                    self.broadcast_ws_message(&dataset, &message),
+                Err(_) => info!("Could not store a resampled record."),
             }
-            Ok(record) => {
-                // Send update message.
-                let message = compose_push_record_message(&record);
-                self.broadcast_ws_message(&message);
+        }
+
+        self.has_ended = true;
+
+        let message = WsMessage::Text(
+            compose_stream_ended_message(&self.totals, &self.storages)
+                .to_string(),
+        );
+        for session in self.sessions.values() {
+            let _ = session.addr.do_send(message.clone());
+        }
+
+        if self.exit_on_eof {
+            self.shut_down(ctx);
+        }
+    }
+}
+
+impl<R, P> Handler<signal::Signal> for Server<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = ();
+
+    fn handle(&mut self, msg: signal::Signal, ctx: &mut Self::Context) {
+        match msg.0 {
+            signal::SignalType::Int
+            | signal::SignalType::Term
+            | signal::SignalType::Quit => {
+                info!("Received a signal.");
+
+                // Stop right away if the user insists.
+                if self.is_shutting_down {
+                    ctx.stop();
+                } else {
+                    self.shut_down(ctx);
+                }
             }
+            _ => {}
         }
     }
 }
 
+/// Get the dataset chosen with the `dataset` query parameter.
+fn query_dataset<S>(req: &HttpRequest<S>) -> String {
+    req.query()
+        .get("dataset")
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_DATASET.to_string())
+}
+
+/// Get the sequence number chosen with the `since` query parameter. A
+/// reconnecting client passes the one of the last record it has received.
+fn query_since<S>(
+    req: &HttpRequest<S>,
+) -> Result<Option<u64>, actix_web::Error> {
+    match req.query().get("since") {
+        Some(since) => Ok(Some(since.parse().map_err(error::ErrorBadRequest)?)),
+        None => Ok(None),
+    }
+}
+
+/// Get the instance chosen with the `instance` query parameter. A
+/// reconnecting client passes the one it has received the records from.
+fn query_instance<S>(req: &HttpRequest<S>) -> Option<String> {
+    req.query().get("instance").cloned()
+}
+
+/// Parse the request body with the server's parser and push the records to
+/// the storage.
+///
+/// The dataset is chosen with the `dataset` query parameter. Responds with
+/// the number of accepted and rejected records.
+pub fn post_records<P>(
+    req: &HttpRequest<WsSessionState<Source, P>>,
+) -> FutureResponse<HttpResponse>
+where
+    P: Parser<Source>,
+{
+    let addr = req.state().addr.clone();
+    let settings = req.state().settings.clone();
+    let compression = req.state().compression;
+    let dataset = query_dataset(req);
+
+    req.body()
+        .limit(MAX_BODY_SIZE)
+        .from_err()
+        .and_then(move |body| {
+            decompress(Box::new(io::Cursor::new(body)), compression)
+                .map_err(error::ErrorBadRequest)
+        })
+        .and_then(move |reader| {
+            let mut inputs = vec![];
+            let mut read_errors = 0;
+
+            for input in P::wrap_reader(reader, settings) {
+                match input {
+                    Ok(input) => inputs.push(input),
+                    Err(error) => {
+                        read_errors += 1;
+
+                        // The rest of the body cannot be read.
+                        if !error.is_recoverable() {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            addr.send(IngestMessage { dataset, inputs }).from_err().map(
+                move |report| {
+                    HttpResponse::Ok().json(json!({
+                        "accepted": report.accepted,
+                        "filtered": report.filtered,
+                        "rejected": report.rejected + read_errors,
+                    }))
+                },
+            )
+        })
+        .responder()
+}
+
+/// Respond with the dataset chosen with the `dataset` query parameter,
+/// written as an Arrow IPC stream.
+pub fn get_arrow_export<R, P>(
+    req: &HttpRequest<WsSessionState<R, P>>,
+) -> FutureResponse<HttpResponse>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    let dataset = query_dataset(req);
+
+    req.state()
+        .addr
+        .send(ExportArrow { dataset })
+        .from_err()
+        .map(|result| match result {
+            Ok(bytes) => HttpResponse::Ok()
+                .content_type("application/vnd.apache.arrow.stream")
+                .body(bytes),
+            Err(InternalError::NoDataset) => HttpResponse::NotFound().finish(),
+            Err(_) => HttpResponse::InternalServerError().finish(),
+        })
+        .responder()
+}
+
+/// Parse the width or the height of an image, which must be between 1 and
+/// [`MAX_IMAGE_SIZE`] pixels.
+fn parse_image_size(size: &str) -> Option<u32> {
+    size.parse()
+        .ok()
+        .filter(|size| (1..=MAX_IMAGE_SIZE).contains(size))
+}
+
+/// Get the render settings chosen with the query parameters. Returns `None`
+/// if any of them is invalid.
+fn query_render_settings<S>(req: &HttpRequest<S>) -> Option<RenderSettings> {
+    let query = req.query();
+    let mut settings = RenderSettings {
+        x: query.get("x").cloned(),
+        y: query
+            .get("y")
+            .map(|fields| fields.split(',').map(String::from).collect()),
+        ..RenderSettings::default()
+    };
+
+    if let Some(width) = query.get("width") {
+        settings.width = parse_image_size(width)?;
+    }
+    if let Some(height) = query.get("height") {
+        settings.height = parse_image_size(height)?;
+    }
+    if let Some(mark) = query.get("mark") {
+        settings.mark = Mark::from_name(mark)?;
+    }
+
+    Some(settings)
+}
+
+fn get_render<R, P>(
+    req: &HttpRequest<WsSessionState<R, P>>,
+    format: ImageFormat,
+) -> FutureResponse<HttpResponse>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    let dataset = query_dataset(req);
+    let settings = match query_render_settings(req) {
+        Some(settings) => settings,
+        None =>
+            return Box::new(future::ok(HttpResponse::BadRequest().finish())),
+    };
+
+    req.state()
+        .addr
+        .send(Render {
+            dataset,
+            settings,
+            format,
+        })
+        .from_err()
+        .map(move |result| match result {
+            Ok(bytes) => HttpResponse::Ok()
+                .content_type(format.content_type())
+                .body(bytes),
+            Err(InternalError::NoDataset) => HttpResponse::NotFound().finish(),
+            Err(InternalError::Render(RenderError::Png(_))) =>
+                HttpResponse::InternalServerError().finish(),
+            Err(InternalError::Render(_)) =>
+                HttpResponse::BadRequest().finish(),
+            Err(_) => HttpResponse::InternalServerError().finish(),
+        })
+        .responder()
+}
+
+/// Respond with the dataset chosen with the `dataset` query parameter,
+/// rendered as an SVG chart.
+///
+/// The fields are chosen with the `x` and `y` (comma-separated) query
+/// parameters, the size of the image with `width` and `height`, and the
+/// mark with `mark` (`line` or `scatter`).
+pub fn get_svg_render<R, P>(
+    req: &HttpRequest<WsSessionState<R, P>>,
+) -> FutureResponse<HttpResponse>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    get_render(req, ImageFormat::Svg)
+}
+
+/// Respond with the dataset rendered as a PNG chart. Accepts the same query
+/// parameters as [`get_svg_render`].
+pub fn get_png_render<R, P>(
+    req: &HttpRequest<WsSessionState<R, P>>,
+) -> FutureResponse<HttpResponse>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    get_render(req, ImageFormat::Png)
+}
+
 pub fn ws_handshake<R, P>(
     req: &HttpRequest<WsSessionState<R, P>>,
 ) -> Result<HttpResponse, actix_web::Error>
@@ -203,11 +1245,477 @@ where
     R: io::Read,
     P: Parser<R>,
 {
+    let since = query_since(req)?;
+    let instance = query_instance(req);
+
     ws::start(
         req,
         WsSession {
             id: 0,
+            since,
+            instance,
             parser: PhantomData,
         },
     )
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use ::actix_web::{test::TestServer, ws::ClientReader, HttpMessage};
+    use ::arrow::{
+        array::{Float64Array, Int64Array},
+        datatypes::{DataType, Field, Schema},
+        ipc::{reader::StreamReader, writer::StreamWriter},
+        record_batch::RecordBatch,
+    };
+    use ::futures::Stream;
+    use ::pretty_assertions::assert_eq;
+
+    use std::sync::{mpsc::channel, Arc};
+
+    use super::*;
+    use crate::{
+        input::Source,
+        parse::{
+            arrow::ArrowParser,
+            framed::{Framing, MAX_FRAME_SIZE},
+            influx::{InfluxParser, Precision},
+            json::JsonParser,
+            msgpack::MsgpackParser,
+            prometheus::PrometheusParser,
+        },
+        storage::Number,
+        transform::{
+            Aggregate,
+            Resampling,
+            TimeField,
+            TimeUnit,
+            TransformSettings,
+        },
+    };
+
+    /// A body with two valid records, an invalid JSON and a record with
+    /// fields which do not match the dataset's.
+    const BODY: &str = "{\"a\": 1, \"b\": 2}\n\
+                        not json\n\
+                        {\"a\": 3, \"b\": 4}\n\
+                        {\"c\": 5}\n";
+
+    /// Start a server parsing JSON with an empty `default` dataset and
+    /// construct the state of the HTTP app.
+    ///
+    /// Must be called within an actix system, e.g. in the state factory of
+    /// a test server.
+    pub(crate) fn json_state() -> WsSessionState<Source, JsonParser> {
+        let (stop_tx, _) = channel();
+        let addr = Server::new(
+            stop_tx,
+            JsonParser::new(),
+            vec![DEFAULT_DATASET.to_string()],
+            Transform::default(),
+            None,
+            false,
+        )
+        .start();
+
+        WsSessionState::new(addr, (), Compression::None)
+    }
+
+    #[test]
+    fn test_ingest_message() {
+        let mut sys = System::new("test");
+        let addr = json_state().addr;
+        let inputs = BODY.lines().map(String::from).collect();
+
+        let report = sys
+            .block_on(addr.send(IngestMessage {
+                dataset: DEFAULT_DATASET.to_string(),
+                inputs,
+            }))
+            .unwrap();
+
+        assert_eq!(report.accepted, 2);
+        assert_eq!(report.filtered, 0);
+        assert_eq!(report.rejected, 2);
+    }
+
+    #[test]
+    fn test_resample_stored_records() {
+        let mut transform = Transform::new(TransformSettings {
+            time_field: Some(TimeField {
+                name: "t".to_string(),
+                unit: TimeUnit::Milliseconds,
+            }),
+            resampling: Some(Resampling {
+                interval: Duration::from_millis(100),
+                aggregate: Aggregate::Mean,
+            }),
+            ..TransformSettings::default()
+        });
+        let mut storages = HashMap::new();
+        let mut next_seq = 0;
+
+        let mut store = |fields: &[(&'static str, i64)]| {
+            let record = Record(
+                fields
+                    .iter()
+                    .map(|(field, int)| {
+                        (Cow::Borrowed(*field), Number::Int(*int))
+                    })
+                    .collect(),
+            );
+
+            transform_and_store(
+                &mut transform,
+                &mut storages,
+                &mut next_seq,
+                DEFAULT_DATASET.to_string(),
+                record,
+                None,
+                false,
+            )
+        };
+
+        assert_eq!(store(&[("t", 10), ("x", 1)]).unwrap().len(), 1);
+        assert!(store(&[("t", 20), ("x", 100), ("y", 2)]).is_err());
+        assert_eq!(store(&[("t", 150), ("x", 5)]).unwrap().len(), 2);
+
+        let resampled = &storages[&format!("{}.resampled", DEFAULT_DATASET)];
+        assert_eq!(
+            ::serde_json::to_value(resampled).unwrap(),
+            json!([{ "t": 0.0, "x": 1.0 }])
+        );
+    }
+
+    #[test]
+    fn test_post_records() {
+        let mut srv = TestServer::build_with_state(json_state).start(|app| {
+            app.resource("/api/records", |r| r.f(post_records));
+        });
+
+        let request = srv
+            .client(http::Method::POST, "/api/records")
+            .body(BODY)
+            .unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert!(response.status().is_success());
+
+        let body = srv.execute(response.body()).unwrap();
+        let report: Value = ::serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            report,
+            json!({ "accepted": 2, "filtered": 0, "rejected": 2 })
+        );
+    }
+
+    #[test]
+    fn test_post_oversized_frame() {
+        let mut srv = TestServer::build_with_state(|| {
+            let (stop_tx, _) = channel();
+            let addr = Server::new(
+                stop_tx,
+                MsgpackParser::new(),
+                vec![DEFAULT_DATASET.to_string()],
+                Transform::default(),
+                None,
+                false,
+            )
+            .start();
+
+            WsSessionState::new(
+                addr,
+                Framing::LengthDelimited,
+                Compression::None,
+            )
+        })
+        .start(|app| {
+            app.resource("/api/records", |r| r.f(post_records));
+        });
+
+        let record = ::rmp_serde::to_vec(&json!({ "a": 1 })).unwrap();
+        let mut body = (MAX_FRAME_SIZE as u32 + 1).to_be_bytes().to_vec();
+        body.extend(vec![0; MAX_FRAME_SIZE + 1]);
+        body.extend(&(record.len() as u32).to_be_bytes());
+        body.extend(&record);
+
+        let request = srv
+            .client(http::Method::POST, "/api/records")
+            .body(body)
+            .unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert!(response.status().is_success());
+
+        // The oversized frame does not stop the reader.
+        let body = srv.execute(response.body()).unwrap();
+        let report: Value = ::serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            report,
+            json!({ "accepted": 1, "filtered": 0, "rejected": 1 })
+        );
+    }
+
+    #[test]
+    fn test_render_size() {
+        let mut srv = TestServer::build_with_state(json_state).start(|app| {
+            app.resource("/api/records", |r| r.f(post_records))
+                .resource("/render.png", |r| r.f(get_png_render));
+        });
+
+        let request = srv
+            .client(http::Method::POST, "/api/records")
+            .body(BODY)
+            .unwrap();
+        srv.execute(request.send()).unwrap();
+
+        for query in &[
+            "width=100000&height=100000",
+            "width=4097",
+            "height=0",
+            "width=-1",
+        ] {
+            let request = srv
+                .client(http::Method::GET, &format!("/render.png?{}", query))
+                .finish()
+                .unwrap();
+            let response = srv.execute(request.send()).unwrap();
+
+            assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        }
+
+        let request = srv
+            .client(http::Method::GET, "/render.png?width=4096&height=200")
+            .finish()
+            .unwrap();
+        let response = srv.execute(request.send()).unwrap();
+
+        assert!(response.status().is_success());
+    }
+
+    #[test]
+    fn test_ingest_empty_record() {
+        let mut sys = System::new("test");
+        let addr = json_state().addr;
+        let inputs = vec!["{}".to_string(), "{\"a\": 1}".to_string()];
+
+        let report = sys
+            .block_on(addr.send(IngestMessage {
+                dataset: DEFAULT_DATASET.to_string(),
+                inputs,
+            }))
+            .unwrap();
+
+        assert_eq!(report.accepted, 1);
+        assert_eq!(report.filtered, 1);
+        assert_eq!(report.rejected, 0);
+    }
+
+    #[test]
+    fn test_ingest_influx() {
+        let mut sys = System::new("test");
+        let (stop_tx, _) = channel();
+        let parser = InfluxParser::new(false, Precision::Nanoseconds);
+        let addr: Addr<Server<Source, _>> = Server::new(
+            stop_tx,
+            parser,
+            vec![],
+            Transform::default(),
+            None,
+            false,
+        )
+        .start();
+        let dataset = "cpu,host=a".to_string();
+
+        let report = sys
+            .block_on(addr.send(IngestMessage {
+                dataset: DEFAULT_DATASET.to_string(),
+                inputs: vec![
+                    format!("{} usage=1,cores=4i 1", dataset),
+                    format!("{} usage=2 2", dataset),
+                ],
+            }))
+            .unwrap();
+        assert_eq!(report.accepted, 2);
+        assert_eq!(report.rejected, 0);
+
+        let bytes = sys
+            .block_on(addr.send(ExportArrow { dataset }))
+            .unwrap()
+            .unwrap();
+        let reader = StreamReader::try_new(&bytes[..], None).unwrap();
+
+        assert_eq!(reader.schema().metadata()["host"], "a");
+    }
+
+    #[test]
+    fn test_ingest_arrow_batches() {
+        let mut sys = System::new("test");
+        let (stop_tx, _) = channel();
+        let addr: Addr<Server<Source, _>> = Server::new(
+            stop_tx,
+            ArrowParser::new(),
+            vec![DEFAULT_DATASET.to_string()],
+            Transform::default(),
+            None,
+            false,
+        )
+        .start();
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, false),
+            Field::new("b", DataType::Float64, true),
+        ]));
+        let mut writer = StreamWriter::try_new(vec![], &schema).unwrap();
+        for (a, b) in [
+            (vec![1, 2], vec![Some(1.0), None]),
+            (vec![3, 4], vec![Some(3.0), Some(4.0)]),
+        ] {
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int64Array::from(a)),
+                    Arc::new(Float64Array::from(b)),
+                ],
+            )
+            .unwrap();
+            writer.write(&batch).unwrap();
+        }
+        writer.finish().unwrap();
+        let contents = writer.into_inner().unwrap();
+
+        let inputs =
+            <ArrowParser as Parser<&[u8]>>::wrap_reader(&contents[..], ())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+        let report = sys
+            .block_on(addr.send(IngestMessage {
+                dataset: DEFAULT_DATASET.to_string(),
+                inputs,
+            }))
+            .unwrap();
+
+        // The row with a null is rejected, the other ones are appended.
+        assert_eq!(report.accepted, 3);
+        assert_eq!(report.rejected, 1);
+
+        let bytes = sys
+            .block_on(addr.send(ExportArrow {
+                dataset: DEFAULT_DATASET.to_string(),
+            }))
+            .unwrap()
+            .unwrap();
+        let batch = StreamReader::try_new(&bytes[..], None)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let a = batch
+            .column_by_name("a")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+
+        assert_eq!(a.values().to_vec(), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn test_ingest_new_prometheus_series() {
+        let mut sys = System::new("test");
+        let (stop_tx, _) = channel();
+        let parser = PrometheusParser::new(vec![]);
+        let addr: Addr<Server<Source, _>> = Server::new(
+            stop_tx,
+            parser,
+            vec![DEFAULT_DATASET.to_string()],
+            Transform::default(),
+            None,
+            false,
+        )
+        .start();
+        let inputs = <PrometheusParser as Parser<&[u8]>>::wrap_reader(
+            &b"up 1\n# EOF\nup 1\nrequests 5\n# EOF\n"[..],
+            (),
+        )
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+        let report = sys
+            .block_on(addr.send(IngestMessage {
+                dataset: DEFAULT_DATASET.to_string(),
+                inputs,
+            }))
+            .unwrap();
+
+        assert_eq!(report.accepted, 2);
+        assert_eq!(report.rejected, 0);
+    }
+
+    /// Read the methods of the next `count` messages and the params of the
+    /// first one.
+    fn read_messages(
+        srv: &mut TestServer,
+        mut reader: ClientReader,
+        count: usize,
+    ) -> (Vec<String>, Value) {
+        let mut methods = vec![];
+        let mut first_params = Value::Null;
+
+        for _ in 0..count {
+            let (message, rest) =
+                srv.execute(reader.into_future()).ok().unwrap();
+            reader = rest;
+
+            let message: Value = match message {
+                Some(ws::Message::Text(text)) =>
+                    ::serde_json::from_str(&text).unwrap(),
+                other => panic!("unexpected message: {:?}", other),
+            };
+            if methods.is_empty() {
+                first_params = message["params"].clone();
+            }
+            methods.push(message["method"].as_str().unwrap().to_string());
+        }
+
+        (methods, first_params)
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut srv = TestServer::build_with_state(json_state).start(|app| {
+            app.resource("/ws/", |r| r.f(ws_handshake));
+            app.resource("/api/records", |r| r.f(post_records));
+        });
+
+        let (reader, _writer) = srv.ws_at("/ws/").unwrap();
+        let (methods, params) = read_messages(&mut srv, reader, 2);
+        assert_eq!(methods, vec!["serverInfo", "initStorageDone"]);
+        let instance = params["instance"].as_str().unwrap().to_string();
+
+        let request = srv
+            .client(http::Method::POST, "/api/records")
+            .body("{\"a\": 1}\n{\"a\": 2}\n")
+            .unwrap();
+        srv.execute(request.send()).unwrap();
+
+        // Only the records after the first one are sent.
+        let path = format!("/ws/?since=0&instance={}", instance);
+        let (reader, _writer) = srv.ws_at(&path).unwrap();
+        let (methods, _) = read_messages(&mut srv, reader, 2);
+        assert_eq!(methods, vec!["serverInfo", "pushRecord"]);
+
+        // The sequence number comes from another run, so all the records
+        // are sent again.
+        let (reader, _writer) =
+            srv.ws_at("/ws/?since=0&instance=0123456789abcdef").unwrap();
+        let (methods, _) = read_messages(&mut srv, reader, 3);
+        assert_eq!(
+            methods,
+            vec!["serverInfo", "initStorageChunk", "initStorageDone"]
+        );
+
+        // Neither can a client resume without the instance.
+        let (reader, _writer) = srv.ws_at("/ws/?since=0").unwrap();
+        let (methods, _) = read_messages(&mut srv, reader, 2);
+        assert_eq!(methods, vec!["serverInfo", "initStorageChunk"]);
+    }
+}
@@ -0,0 +1,119 @@
+//! Requests sent by the clients.
+
+use ::serde_json::{self as json, Value};
+
+/// A request sent by a client over WS.
+///
+/// The requests follow the [JSON RPC](https://en.wikipedia.org/wiki/JSON-RPC)
+/// format, just like the messages sent by the server.
+#[derive(Debug, PartialEq)]
+pub enum Request {
+    /// Receive only the records from the datasets. `None` means all datasets.
+    Subscribe { datasets: Option<Vec<String>> },
+    /// Receive the records with sequence numbers greater than `since`,
+    /// received from the instance.
+    Resume {
+        since: u64,
+        instance: Option<String>,
+    },
+}
+
+impl Request {
+    /// Parse the request. Returns `None` if the request is invalid.
+    pub fn parse(text: &str) -> Option<Self> {
+        let request = json::from_str::<Value>(text).ok()?;
+        let params = &request["params"];
+
+        match request["method"].as_str()? {
+            "subscribe" => {
+                let datasets = match &params["datasets"] {
+                    Value::Null => None,
+                    Value::Array(datasets) => Some(
+                        datasets
+                            .iter()
+                            .map(|dataset| dataset.as_str().map(String::from))
+                            .collect::<Option<Vec<_>>>()?,
+                    ),
+                    _ => return None,
+                };
+
+                Some(Request::Subscribe { datasets })
+            }
+            "resume" => Some(Request::Resume {
+                since: params["since"].as_u64()?,
+                instance: match &params["instance"] {
+                    Value::Null => None,
+                    Value::String(instance) => Some(instance.clone()),
+                    _ => return None,
+                },
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_subscribe_request() {
+        let text =
+            r#"{ "method": "subscribe", "params": { "datasets": ["a"] } }"#;
+
+        assert_eq!(
+            Request::parse(text),
+            Some(Request::Subscribe {
+                datasets: Some(vec!["a".to_string()]),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_subscribe_to_all_request() {
+        let text = r#"{ "method": "subscribe", "params": {} }"#;
+
+        assert_eq!(
+            Request::parse(text),
+            Some(Request::Subscribe { datasets: None })
+        );
+    }
+
+    #[test]
+    fn test_parse_resume_request() {
+        let text = r#"{ "method": "resume", "params": { "since": 41 } }"#;
+
+        assert_eq!(
+            Request::parse(text),
+            Some(Request::Resume {
+                since: 41,
+                instance: None,
+            })
+        );
+
+        let text = r#"{
+            "method": "resume",
+            "params": { "since": 41, "instance": "00ff" }
+        }"#;
+
+        assert_eq!(
+            Request::parse(text),
+            Some(Request::Resume {
+                since: 41,
+                instance: Some("00ff".to_string()),
+            })
+        );
+        assert_eq!(
+            Request::parse(r#"{ "method": "resume", "params": {} }"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_request() {
+        assert_eq!(Request::parse(r#"{ "method": "unknown" }"#), None);
+        assert_eq!(Request::parse("not json"), None);
+    }
+}
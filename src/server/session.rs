@@ -3,14 +3,16 @@ use ::actix_web::*;
 
 use std::io;
 
-use super::*;
+use super::{request::Request, *};
 
 pub struct WsSessionState<R, P>
 where
     R: 'static + io::Read,
     P: Parser<R>,
 {
-    addr: Addr<Server<R, P>>,
+    pub(super) addr: Addr<Server<R, P>>,
+    pub(super) settings: P::Settings,
+    pub(super) compression: Compression,
 }
 
 impl<R, P> WsSessionState<R, P>
@@ -18,8 +20,18 @@ where
     R: io::Read,
     P: Parser<R>,
 {
-    pub fn new(addr: Addr<Server<R, P>>) -> Self {
-        Self { addr }
+    /// Create the state of the HTTP app. The request bodies are wrapped
+    /// with the settings and decompressed before being sent to the server.
+    pub fn new(
+        addr: Addr<Server<R, P>>,
+        settings: P::Settings,
+        compression: Compression,
+    ) -> Self {
+        Self {
+            addr,
+            settings,
+            compression,
+        }
     }
 }
 
@@ -29,6 +41,11 @@ where
     P: Parser<R>,
 {
     pub id: usize,
+    /// The sequence number of the last record received by the client before
+    /// it reconnected.
+    pub since: Option<u64>,
+    /// The instance the client has received the record from.
+    pub instance: Option<String>,
     pub parser: PhantomData<(R, P)>,
 }
 
@@ -37,7 +54,24 @@ where
     R: io::Read,
     P: Parser<R>,
 {
-    fn handle(&mut self, _msg: ws::Message, _ctx: &mut Self::Context) {}
+    fn handle(&mut self, msg: ws::Message, ctx: &mut Self::Context) {
+        if let ws::Message::Text(text) = msg {
+            match Request::parse(&text) {
+                Some(Request::Subscribe { datasets }) =>
+                    ctx.state().addr.do_send(Subscribe {
+                        id: self.id,
+                        datasets,
+                    }),
+                Some(Request::Resume { since, instance }) =>
+                    ctx.state().addr.do_send(Resume {
+                        id: self.id,
+                        since,
+                        instance,
+                    }),
+                None => info!("Received an invalid request."),
+            }
+        }
+    }
 }
 
 impl<R, P> Actor for WsSession<R, P>
@@ -54,6 +88,8 @@ where
             .addr
             .send(Connect {
                 addr: addr.recipient(),
+                since: self.since,
+                instance: self.instance.clone(),
             })
             .into_actor(self)
             .then(|res, act, ctx| {
@@ -82,6 +118,9 @@ where
     type Result = ();
 
     fn handle(&mut self, msg: WsMessage, ctx: &mut Self::Context) {
-        ctx.text(msg.0);
+        match msg {
+            WsMessage::Text(text) => ctx.text(text),
+            WsMessage::Close => ctx.close(Some(ws::CloseCode::Normal.into())),
+        }
     }
 }
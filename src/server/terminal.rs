@@ -0,0 +1,158 @@
+//! Live chart drawn in the terminal.
+
+use ::actix::*;
+use ::terminal_size::{terminal_size, Height, Width};
+
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use super::*;
+
+/// How often the chart is redrawn, at most.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The size of the terminal assumed when it cannot be read.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// The chart drawn in the terminal.
+pub struct TerminalView {
+    /// The drawn dataset.
+    pub dataset: String,
+    /// Render settings. The size is taken from the terminal.
+    pub settings: RenderSettings,
+}
+
+/// A session drawing a dataset in the terminal.
+///
+/// It connects to the server just like a WS session and redraws the chart
+/// whenever the dataset has been updated.
+pub struct TerminalSession<R, P>
+where
+    R: 'static + io::Read,
+    P: Parser<R>,
+{
+    addr: Addr<Server<R, P>>,
+    view: TerminalView,
+    id: Option<usize>,
+    is_outdated: bool,
+}
+
+impl<R, P> TerminalSession<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    /// Create a session drawing the view of the server's dataset.
+    pub fn new(addr: Addr<Server<R, P>>, view: TerminalView) -> Self {
+        Self {
+            addr,
+            view,
+            id: None,
+            is_outdated: true,
+        }
+    }
+
+    fn redraw(&mut self, ctx: &mut Context<Self>) {
+        if !self.is_outdated {
+            return;
+        }
+        self.is_outdated = false;
+
+        let (columns, rows) = terminal_size()
+            .map(|(Width(columns), Height(rows))| (columns, rows))
+            .unwrap_or(DEFAULT_SIZE);
+        // Leave the last row empty so that the terminal does not scroll.
+        let settings = RenderSettings {
+            width: u32::from(columns),
+            height: u32::from(rows.saturating_sub(1)),
+            ..self.view.settings.clone()
+        };
+
+        self.addr
+            .send(RenderTerminal {
+                dataset: self.view.dataset.clone(),
+                settings,
+            })
+            .into_actor(self)
+            .then(|result, _, _| {
+                match result {
+                    Ok(Ok(text)) => {
+                        let stdout = io::stdout();
+                        let mut stdout = stdout.lock();
+
+                        // Draw over the previous chart.
+                        let _ = write!(
+                            stdout,
+                            "\x1b[H{}\x1b[K\x1b[J",
+                            text.replace('\n', "\x1b[K\n")
+                        );
+                        let _ = stdout.flush();
+                    }
+                    _ => info!("Could not draw the chart."),
+                }
+
+                fut::ok(())
+            })
+            .wait(ctx);
+    }
+}
+
+impl<R, P> Actor for TerminalSession<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.addr
+            .send(Connect {
+                addr: ctx.address().recipient(),
+                since: None,
+                instance: None,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(id) => {
+                        act.id = Some(id);
+                        act.addr.do_send(Subscribe {
+                            id,
+                            datasets: Some(vec![act.view.dataset.clone()]),
+                        });
+                    }
+                    _ => ctx.stop(),
+                }
+
+                fut::ok(())
+            })
+            .wait(ctx);
+
+        ctx.run_interval(REDRAW_INTERVAL, |act, ctx| act.redraw(ctx));
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        if let Some(id) = self.id {
+            self.addr.do_send(Disconnect { id });
+        }
+
+        Running::Stop
+    }
+}
+
+impl<R, P> Handler<WsMessage> for TerminalSession<R, P>
+where
+    R: io::Read,
+    P: Parser<R>,
+{
+    type Result = ();
+
+    fn handle(&mut self, msg: WsMessage, ctx: &mut Self::Context) {
+        match msg {
+            WsMessage::Text(_) => self.is_outdated = true,
+            WsMessage::Close => ctx.stop(),
+        }
+    }
+}
@@ -5,7 +5,12 @@ use ::serde::{
     *,
 };
 
-use std::{borrow::Cow, collections::HashMap, fmt};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    ops::Range,
+};
 
 use crate::parse::record::Record;
 
@@ -100,6 +105,16 @@ pub enum Number {
     Int(i64),
 }
 
+impl Number {
+    /// Get the number as a float.
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Number::Float(float) => float,
+            Number::Int(int) => int as f64,
+        }
+    }
+}
+
 impl From<f64> for Number {
     fn from(number: f64) -> Self {
         Number::Float(number)
@@ -137,22 +152,43 @@ impl fmt::Display for StorageError {
     }
 }
 
+/// Labels describing all the records of a dataset, e.g. the tags of an
+/// InfluxDB series.
+pub type Labels = BTreeMap<String, String>;
+
 /// Record storage.
+///
+/// Every record is stored along with its sequence number. The sequence
+/// numbers are increasing, but not necessarily consecutive.
 #[derive(Debug)]
 pub struct Storage {
     inner: HashMap<String, NumberVec>,
+    seqs: Vec<u64>,
+    labels: Labels,
     is_empty: bool,
 }
 
 impl Storage {
     /// Construct new storage.
     pub fn new() -> Self {
+        Self::with_labels(Labels::new())
+    }
+
+    /// Construct new storage with the labels of its records.
+    pub fn with_labels(labels: Labels) -> Self {
         Storage {
             inner: HashMap::new(),
+            seqs: vec![],
+            labels,
             is_empty: true,
         }
     }
 
+    /// Get the labels of the records.
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
     pub fn is_empty(&self) -> bool {
         self.is_empty
     }
@@ -161,6 +197,68 @@ impl Storage {
         self.inner.values().next().map(|vec| vec.len()).unwrap_or(0)
     }
 
+    /// Get the sequence number of the first stored record.
+    pub fn first_seq(&self) -> Option<u64> {
+        self.seqs.first().cloned()
+    }
+
+    /// Get the sequence number of the last stored record.
+    pub fn last_seq(&self) -> Option<u64> {
+        self.seqs.last().cloned()
+    }
+
+    /// Iterate over the records with sequence numbers greater than `seq`,
+    /// along with their sequence numbers.
+    pub fn records_since(
+        &self,
+        seq: u64,
+    ) -> impl Iterator<Item = (u64, Record<'_>)> {
+        self.records_from(self.seqs.partition_point(|stored| *stored <= seq))
+    }
+
+    /// Iterate over the records starting from the one at the index, along
+    /// with their sequence numbers.
+    pub fn records_from(
+        &self,
+        start: usize,
+    ) -> impl Iterator<Item = (u64, Record<'_>)> {
+        (start..self.seqs.len()).map(move |index| {
+            let record = self
+                .inner
+                .iter()
+                .map(|(field, column)| {
+                    (Cow::Borrowed(field.as_str()), column.get(index).unwrap())
+                })
+                .collect();
+
+            (self.seqs[index], Record(record))
+        })
+    }
+
+    /// Split the records into chunks of at most `len` records, each one
+    /// serialized straight from the columns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is 0.
+    pub fn chunks(&self, len: usize) -> impl Iterator<Item = StorageChunk<'_>> {
+        let records_len = self.records_len();
+
+        (0..records_len)
+            .step_by(len)
+            .map(move |start| StorageChunk {
+                storage: self,
+                range: start..records_len.min(start + len),
+            })
+    }
+
+    /// Iterate over the fields and their columns.
+    pub fn columns(&self) -> impl Iterator<Item = (&str, &NumberVec)> {
+        self.inner
+            .iter()
+            .map(|(field, column)| (field.as_str(), column))
+    }
+
     fn push_record_first<'a>(
         &mut self,
         record: &'a Record<'a>,
@@ -229,16 +327,139 @@ impl Storage {
         }
     }
 
-    /// Push a record to the storage.
+    /// Push a record to the storage. Its sequence number follows the one of
+    /// the last stored record.
     pub fn push_record<'a>(
         &mut self,
         record: &'a Record<'a>,
     ) -> Result<(), StorageError> {
+        let seq = self.last_seq().map_or(0, |seq| seq + 1);
+
+        self.push_record_with_seq(record, seq)
+    }
+
+    /// Push a record with the sequence number to the storage. The sequence
+    /// number must be greater than the one of the last stored record.
+    pub fn push_record_with_seq<'a>(
+        &mut self,
+        record: &'a Record<'a>,
+        seq: u64,
+    ) -> Result<(), StorageError> {
+        debug_assert!(self.last_seq().is_none_or(|last| last < seq));
+
         if !self.is_empty() {
-            self.push_record_next(record)
+            self.push_record_next(record)?;
+        } else {
+            self.push_record_first(record)?;
+        }
+        self.seqs.push(seq);
+
+        Ok(())
+    }
+
+    /// Append the columns of many records to the storage, with consecutive
+    /// sequence numbers starting from `seq`.
+    ///
+    /// All the columns must have the same length. Just like a single record,
+    /// they must match the stored fields and their types.
+    pub fn push_columns_with_seq(
+        &mut self,
+        columns: HashMap<String, NumberVec>,
+        seq: u64,
+    ) -> Result<(), StorageError> {
+        debug_assert!(self.last_seq().is_none_or(|last| last < seq));
+
+        let len = match columns.values().next() {
+            Some(column) => column.len(),
+            None => return Err(StorageError::EmptyRecord),
+        };
+        debug_assert!(columns.values().all(|column| column.len() == len));
+
+        if self.is_empty() {
+            self.inner = columns;
+            self.is_empty = false;
         } else {
-            self.push_record_first(record)
+            if columns.len() != self.inner.len()
+                || !columns.keys().all(|key| self.inner.contains_key(key))
+            {
+                return Err(StorageError::FieldMismatch);
+            }
+
+            let types_match = columns.iter().all(|(key, column)| {
+                matches!(
+                    (&self.inner[key], column),
+                    (NumberVec::Int(_), NumberVec::Int(_))
+                        | (NumberVec::Float(_), NumberVec::Float(_))
+                )
+            });
+
+            if !types_match {
+                return Err(StorageError::FieldTypeMismatch);
+            }
+
+            for (key, column) in columns {
+                match (self.inner.get_mut(&key).unwrap(), column) {
+                    (NumberVec::Int(vec), NumberVec::Int(column)) =>
+                        vec.extend(column),
+                    (NumberVec::Float(vec), NumberVec::Float(column)) =>
+                        vec.extend(column),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        self.seqs.extend(seq..seq + len as u64);
+
+        Ok(())
+    }
+
+    /// Push a record which may lack some of the stored fields or carry new
+    /// ones, with the sequence number.
+    ///
+    /// The missing values are stored as NaN, so a column lacking a value
+    /// becomes a float one. So do the new fields, which are NaN in the
+    /// previous records.
+    pub fn push_sparse_record_with_seq<'a>(
+        &mut self,
+        record: &'a Record<'a>,
+        seq: u64,
+    ) -> Result<(), StorageError> {
+        if self.is_empty() {
+            return self.push_record_with_seq(record, seq);
+        }
+
+        debug_assert!(self.last_seq().is_none_or(|last| last < seq));
+
+        let len = self.records_len();
+        for field in record.0.keys() {
+            if !self.inner.contains_key(&**field) {
+                self.inner.insert(
+                    field.clone().into_owned(),
+                    NumberVec::Float(vec![f64::NAN; len]),
+                );
+            }
+        }
+
+        for (field, column) in self.inner.iter_mut() {
+            let number = record.0.get(field.as_str()).cloned();
+
+            match (&mut *column, number) {
+                (NumberVec::Int(vec), Some(Number::Int(int))) => vec.push(int),
+                (NumberVec::Float(vec), number) =>
+                    vec.push(number.map_or(f64::NAN, |number| number.as_f64())),
+                (NumberVec::Int(vec), number) => {
+                    let mut floats =
+                        vec.iter().map(|int| *int as f64).collect::<Vec<_>>();
+                    floats.push(
+                        number.map_or(f64::NAN, |number| number.as_f64()),
+                    );
+
+                    *column = NumberVec::Float(floats);
+                }
+            }
         }
+        self.seqs.push(seq);
+
+        Ok(())
     }
 }
 
@@ -258,39 +479,206 @@ impl Serialize for Storage {
     where
         S: Serializer,
     {
-        struct StorageAndIndex<'a>(&'a HashMap<String, NumberVec>, usize);
+        StorageChunk {
+            storage: self,
+            range: 0..self.records_len(),
+        }
+        .serialize(serializer)
+    }
+}
 
-        impl<'a> Serialize for StorageAndIndex<'a> {
-            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-            where
-                S: Serializer,
-            {
-                let mut map = serializer.serialize_map(Some(self.0.len()))?;
-
-                self.0
-                    .iter()
-                    .map(|(k, v)| match v {
-                        NumberVec::Float(vec) =>
-                            map.serialize_entry(k, &vec[self.1]),
-                        NumberVec::Int(vec) =>
-                            map.serialize_entry(k, &vec[self.1]),
-                    })
-                    .collect::<Result<_, _>>()?;
-
-                map.end()
-            }
+/// A range of the records of [`Storage`], serialized as a list of records.
+pub struct StorageChunk<'a> {
+    storage: &'a Storage,
+    range: Range<usize>,
+}
+
+impl<'a> StorageChunk<'a> {
+    /// Get the index of the first record of the chunk in the storage.
+    pub fn offset(&self) -> usize {
+        self.range.start
+    }
+
+    /// Get the number of records in the chunk.
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Check whether the chunk is empty.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+}
+
+impl<'a> Serialize for StorageChunk<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let storage = &self.storage.inner;
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+
+        self.range.clone().try_for_each(|index| {
+            seq.serialize_element(&StorageAndIndex(storage, index))
+        })?;
+
+        seq.end()
+    }
+}
+
+/// A single record of [`Storage`], serialized as a map of its fields.
+struct StorageAndIndex<'a>(&'a HashMap<String, NumberVec>, usize);
+
+impl<'a> Serialize for StorageAndIndex<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+
+        self.0.iter().try_for_each(|(k, v)| match v {
+            NumberVec::Float(vec) => map.serialize_entry(k, &vec[self.1]),
+            NumberVec::Int(vec) => map.serialize_entry(k, &vec[self.1]),
+        })?;
+
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn record(x: i64) -> Record<'static> {
+        Record(
+            vec![(Cow::Borrowed("x"), Number::Int(x))]
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_records_since() {
+        let mut storage = Storage::new();
+        storage.push_record_with_seq(&record(10), 3).unwrap();
+        storage.push_record_with_seq(&record(11), 5).unwrap();
+        storage.push_record_with_seq(&record(12), 6).unwrap();
+
+        assert_eq!(storage.first_seq(), Some(3));
+        assert_eq!(storage.last_seq(), Some(6));
+
+        fn since(storage: &Storage, seq: u64) -> Vec<(u64, Number)> {
+            storage
+                .records_since(seq)
+                .map(|(seq, record)| (seq, record.0["x"]))
+                .collect()
         }
 
-        let storage = &self.inner;
-        let records_len = self.records_len();
-        let mut seq = serializer.serialize_seq(Some(records_len))?;
+        assert_eq!(
+            since(&storage, 0),
+            vec![
+                (3, Number::Int(10)),
+                (5, Number::Int(11)),
+                (6, Number::Int(12)),
+            ]
+        );
+        assert_eq!(
+            since(&storage, 4),
+            vec![(5, Number::Int(11)), (6, Number::Int(12)),]
+        );
+        assert_eq!(since(&storage, 6), vec![]);
+
+        storage.push_record(&record(13)).unwrap();
+
+        assert_eq!(since(&storage, 6), vec![(7, Number::Int(13))]);
+    }
 
-        (0..records_len)
-            .map(|index| {
-                seq.serialize_element(&StorageAndIndex(storage, index))
+    #[test]
+    fn test_chunks() {
+        let mut storage = Storage::new();
+        for x in 0..5 {
+            storage.push_record(&record(x)).unwrap();
+        }
+
+        let chunks = storage
+            .chunks(2)
+            .map(|chunk| {
+                (chunk.offset(), serde_json::to_string(&chunk).unwrap())
             })
-            .collect::<Result<_, _>>()?;
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            chunks,
+            vec![
+                (0, r#"[{"x":0},{"x":1}]"#.to_string()),
+                (2, r#"[{"x":2},{"x":3}]"#.to_string()),
+                (4, r#"[{"x":4}]"#.to_string()),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&storage).unwrap(),
+            r#"[{"x":0},{"x":1},{"x":2},{"x":3},{"x":4}]"#
+        );
+        assert_eq!(Storage::new().chunks(2).count(), 0);
+    }
 
-        seq.end()
+    #[test]
+    fn test_push_sparse_records() {
+        let mut storage = Storage::new();
+        storage.push_sparse_record_with_seq(&record(1), 0).unwrap();
+
+        let mut with_y = record(2);
+        with_y.0.insert(Cow::Borrowed("y"), Number::Int(20));
+        storage.push_sparse_record_with_seq(&with_y, 1).unwrap();
+
+        let mut only_y = Record(HashMap::new());
+        only_y.0.insert(Cow::Borrowed("y"), Number::Int(30));
+        storage.push_sparse_record_with_seq(&only_y, 2).unwrap();
+
+        assert_eq!(storage.records_len(), 3);
+        assert_eq!(
+            serde_json::to_string(storage["x"].float().unwrap()).unwrap(),
+            "[1.0,2.0,null]"
+        );
+        assert_eq!(
+            serde_json::to_string(storage["y"].float().unwrap()).unwrap(),
+            "[null,20.0,30.0]"
+        );
+
+        // The complete records are stored just like with `push_record`.
+        storage.push_sparse_record_with_seq(&with_y, 3).unwrap();
+
+        assert_eq!(storage.records_len(), 4);
+    }
+
+    #[test]
+    fn test_push_columns() {
+        fn columns(x: Vec<i64>) -> HashMap<String, NumberVec> {
+            vec![("x".to_string(), NumberVec::Int(x))]
+                .into_iter()
+                .collect()
+        }
+
+        let mut storage = Storage::new();
+        storage
+            .push_columns_with_seq(columns(vec![1, 2]), 0)
+            .unwrap();
+        storage.push_record_with_seq(&record(3), 2).unwrap();
+        storage
+            .push_columns_with_seq(columns(vec![4, 5]), 3)
+            .unwrap();
+
+        assert_eq!(storage["x"].int().unwrap(), &vec![1, 2, 3, 4, 5]);
+        assert_eq!(storage.last_seq(), Some(4));
+
+        let floats = vec![("x".to_string(), NumberVec::Float(vec![6.0]))]
+            .into_iter()
+            .collect();
+
+        assert!(storage.push_columns_with_seq(floats, 5).is_err());
+        assert!(storage.push_columns_with_seq(HashMap::new(), 5).is_err());
+        assert_eq!(storage.records_len(), 5);
     }
 }
@@ -0,0 +1,144 @@
+//! TLS configuration of the HTTP server.
+
+use ::rustls::{
+    internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys},
+    NoClientAuth,
+    ServerConfig,
+    TLSError,
+};
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+/// TLS configuration error.
+#[derive(Debug)]
+pub enum TlsError {
+    /// A file could not be read.
+    Io(io::Error),
+    /// The certificate file contains no PEM certificates.
+    NoCertificates,
+    /// The key file contains no PEM private key, either PKCS #8 or RSA.
+    NoPrivateKey,
+    /// The certificate or the key has been rejected.
+    Rustls(TLSError),
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TlsError::Io(error) => write!(f, "io error: {}", error),
+            TlsError::NoCertificates => write!(f, "no certificates"),
+            TlsError::NoPrivateKey => write!(f, "no private key"),
+            TlsError::Rustls(error) => write!(f, "tls error: {}", error),
+        }
+    }
+}
+
+/// Build the server config from the PEM files with the certificate chain and
+/// the private key.
+pub fn load_tls_config(
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<ServerConfig, TlsError> {
+    let open =
+        |path| File::open(path).map(BufReader::new).map_err(TlsError::Io);
+
+    let cert_chain = certs(&mut open(cert_path)?)
+        .ok()
+        .filter(|chain| !chain.is_empty())
+        .ok_or(TlsError::NoCertificates)?;
+
+    // Try PKCS #8 keys first, they are what most tools generate nowadays.
+    let key = pkcs8_private_keys(&mut open(key_path)?)
+        .ok()
+        .and_then(|keys| keys.into_iter().next());
+    let key = match key {
+        Some(key) => key,
+        None => rsa_private_keys(&mut open(key_path)?)
+            .ok()
+            .and_then(|keys| keys.into_iter().next())
+            .ok_or(TlsError::NoPrivateKey)?,
+    };
+
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(cert_chain, key)
+        .map_err(TlsError::Rustls)?;
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod test {
+    use ::actix_web::{http::Method, test::TestServer, ws, HttpMessage};
+    use ::futures::Stream;
+
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::server::{get_client_index, test::json_state, ws_handshake};
+
+    /// A self-signed certificate for `localhost` and its key. The certificate
+    /// is also trusted by the client of actix-web's test server.
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(name)
+    }
+
+    #[test]
+    fn test_load_tls_config() {
+        assert!(
+            load_tls_config(&fixture("cert.pem"), &fixture("key.pem")).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_load_tls_config_errors() {
+        // The files swapped.
+        match load_tls_config(&fixture("key.pem"), &fixture("cert.pem")) {
+            Err(TlsError::NoCertificates) => {}
+            other => panic!("unexpected result: {:?}", other.err()),
+        }
+        match load_tls_config(&fixture("cert.pem"), &fixture("cert.pem")) {
+            Err(TlsError::NoPrivateKey) => {}
+            other => panic!("unexpected result: {:?}", other.err()),
+        }
+        match load_tls_config(&fixture("cert.pem"), &fixture("missing.pem")) {
+            Err(TlsError::Io(_)) => {}
+            other => panic!("unexpected result: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_serve_over_tls() {
+        let config =
+            load_tls_config(&fixture("cert.pem"), &fixture("key.pem")).unwrap();
+        let mut srv = TestServer::build_with_state(json_state)
+            .rustls(config)
+            .start(|app| {
+                app.resource("/", |r| r.f(get_client_index));
+                app.resource("/ws/", |r| r.f(ws_handshake));
+            });
+
+        assert!(srv.url("/").starts_with("https://"));
+
+        let request = srv.client(Method::GET, "/").finish().unwrap();
+        let response = srv.execute(request.send()).unwrap();
+        assert!(response.status().is_success());
+        let body = srv.execute(response.body()).unwrap();
+        assert!(body.starts_with(b"<!DOCTYPE html>"));
+
+        let (reader, _writer) = srv.ws_at("/ws/").unwrap();
+        let (message, _) = srv.execute(reader.into_future()).ok().unwrap();
+        match message {
+            Some(ws::Message::Text(text)) =>
+                assert!(text.contains("serverInfo")),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+}
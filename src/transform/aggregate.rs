@@ -0,0 +1,482 @@
+//! Rolling aggregates and resampling.
+
+use ::humantime::parse_duration;
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{expr::ExprError, parse::record::Record, storage::Number};
+
+/// Aggregate function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregate {
+    /// Arithmetic mean.
+    Mean,
+    /// Minimum.
+    Min,
+    /// Maximum.
+    Max,
+    /// Sum.
+    Sum,
+    /// Population standard deviation.
+    Stddev,
+}
+
+impl Aggregate {
+    /// Names of the variants accepted by [`Aggregate::from_name`].
+    pub const NAMES: &'static [&'static str] =
+        &["mean", "min", "max", "sum", "stddev"];
+
+    /// Get the variant by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mean" => Some(Aggregate::Mean),
+            "min" => Some(Aggregate::Min),
+            "max" => Some(Aggregate::Max),
+            "sum" => Some(Aggregate::Sum),
+            "stddev" => Some(Aggregate::Stddev),
+            _ => None,
+        }
+    }
+}
+
+/// Unit of the time field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeUnit {
+    /// Seconds.
+    Seconds,
+    /// Milliseconds.
+    Milliseconds,
+    /// Microseconds.
+    Microseconds,
+    /// Nanoseconds.
+    Nanoseconds,
+}
+
+impl TimeUnit {
+    /// Names of the variants accepted by [`TimeUnit::from_name`].
+    pub const NAMES: &'static [&'static str] = &["s", "ms", "us", "ns"];
+
+    /// Get the variant by its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "s" => Some(TimeUnit::Seconds),
+            "ms" => Some(TimeUnit::Milliseconds),
+            "us" => Some(TimeUnit::Microseconds),
+            "ns" => Some(TimeUnit::Nanoseconds),
+            _ => None,
+        }
+    }
+
+    /// Express the duration in the unit.
+    fn convert(self, duration: Duration) -> f64 {
+        let seconds = duration.as_secs_f64();
+
+        match self {
+            TimeUnit::Seconds => seconds,
+            TimeUnit::Milliseconds => seconds * 1e3,
+            TimeUnit::Microseconds => seconds * 1e6,
+            TimeUnit::Nanoseconds => seconds * 1e9,
+        }
+    }
+}
+
+/// The field holding the time of the record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeField {
+    /// The name of the field.
+    pub name: String,
+    /// The unit of its values.
+    pub unit: TimeUnit,
+}
+
+impl TimeField {
+    /// Get the time of the record.
+    pub(super) fn get(&self, record: &Record) -> Option<f64> {
+        record
+            .0
+            .get(self.name.as_str())
+            .map(|number| number.as_f64())
+    }
+}
+
+/// Running count, sum, mean and sum of squared deviations of values, the
+/// latter two updated with Welford's method.
+#[derive(Debug, Clone, Copy, Default)]
+struct Moments {
+    count: usize,
+    sum: f64,
+    mean: f64,
+    squared_deviations: f64,
+}
+
+impl Moments {
+    fn push(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.squared_deviations += delta * (value - self.mean);
+    }
+
+    /// Remove a value pushed before.
+    fn pop(&mut self, value: f64) {
+        self.count -= 1;
+        if self.count == 0 {
+            *self = Self::default();
+
+            return;
+        }
+        self.sum -= value;
+
+        let delta = value - self.mean;
+        self.mean -= delta / self.count as f64;
+        self.squared_deviations =
+            (self.squared_deviations - delta * (value - self.mean)).max(0.0);
+    }
+
+    fn mean(&self) -> f64 {
+        if self.count == 0 {
+            f64::NAN
+        } else {
+            self.mean
+        }
+    }
+
+    fn stddev(&self) -> f64 {
+        (self.squared_deviations / self.count as f64).sqrt()
+    }
+}
+
+/// Running moments, minimum and maximum of values.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    moments: Moments,
+    min: f64,
+    max: f64,
+}
+
+impl Accumulator {
+    fn new() -> Self {
+        Self {
+            moments: Moments::default(),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        self.moments.push(value);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn result(&self, aggregate: Aggregate) -> f64 {
+        match aggregate {
+            Aggregate::Mean => self.moments.mean(),
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+            Aggregate::Sum => self.moments.sum,
+            Aggregate::Stddev => self.moments.stddev(),
+        }
+    }
+}
+
+/// The records a rolling aggregate is computed over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    /// The last N records.
+    Records(usize),
+    /// The records not older than the duration, by the time field.
+    Duration(Duration),
+}
+
+/// A field computed as an aggregate of another field over a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rolling {
+    /// The name of the computed field.
+    pub name: String,
+    /// How the values in the window are combined.
+    pub aggregate: Aggregate,
+    /// The aggregated field.
+    pub field: String,
+    /// The records the aggregate is computed over.
+    pub window: Window,
+}
+
+impl FromStr for Rolling {
+    type Err = ExprError;
+
+    /// Parse a rolling aggregate written as `name = aggregate(field, window)`,
+    /// e.g. `loss_avg = mean(loss, 100)` or `temp_max = max(temp, 5s)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ExprError::new(
+                "expected a rolling aggregate written as \
+                 `name = aggregate(field, window)`",
+            )
+        };
+
+        let index = s.find('=').ok_or_else(invalid)?;
+        let name = s[..index].trim();
+        let call = s[index + 1..].trim();
+
+        let open = call.find('(').ok_or_else(invalid)?;
+        if !call.ends_with(')') || name.is_empty() {
+            return Err(invalid());
+        }
+
+        let aggregate = call[..open].trim();
+        let aggregate = Aggregate::from_name(aggregate).ok_or_else(|| {
+            ExprError::new(format!("unknown aggregate `{}`", aggregate))
+        })?;
+
+        let args = &call[open + 1..call.len() - 1];
+        let comma = args.rfind(',').ok_or_else(invalid)?;
+        let field = args[..comma].trim().trim_matches('`');
+        let window = args[comma + 1..].trim();
+
+        let window = match window.parse::<usize>() {
+            Ok(0) => return Err(ExprError::new("the window is empty")),
+            Ok(len) => Window::Records(len),
+            Err(_) =>
+                Window::Duration(parse_duration(window).map_err(|_| {
+                    ExprError::new(format!("invalid window `{}`", window))
+                })?),
+        };
+
+        if field.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            aggregate,
+            field: field.to_string(),
+            window,
+        })
+    }
+}
+
+/// Values of the aggregated field, along with the time of their records.
+///
+/// The moments of the finite values are updated as the values enter and
+/// leave the window, and computed anew once all the values are replaced, so
+/// that the rounding errors do not pile up. The minimum and the maximum are
+/// kept at the front of monotonic queues of the candidates, indexed by the
+/// order of the values.
+#[derive(Debug, Default)]
+pub(super) struct WindowValues {
+    values: VecDeque<(f64, f64)>,
+    pushed: u64,
+    popped: usize,
+    moments: Moments,
+    non_finite: usize,
+    mins: VecDeque<(u64, f64)>,
+    maxs: VecDeque<(u64, f64)>,
+}
+
+impl WindowValues {
+    /// Push the value of the record and compute the aggregate over the window.
+    ///
+    /// Returns `None` if the record lacks the aggregated or the time field.
+    pub(super) fn push(
+        &mut self,
+        rolling: &Rolling,
+        time_field: Option<&TimeField>,
+        record: &Record,
+    ) -> Option<f64> {
+        let value = record.0.get(rolling.field.as_str())?.as_f64();
+
+        match rolling.window {
+            Window::Records(len) => {
+                self.push_back(0.0, value);
+
+                while self.values.len() > len {
+                    self.pop_front();
+                }
+            }
+            Window::Duration(duration) => {
+                let time_field = time_field?;
+                let time = time_field.get(record)?;
+                let start = time - time_field.unit.convert(duration);
+                self.push_back(time, value);
+
+                while self
+                    .values
+                    .front()
+                    .is_some_and(|(time, _)| *time <= start)
+                {
+                    self.pop_front();
+                }
+            }
+        }
+
+        Some(self.result(rolling.aggregate))
+    }
+
+    fn push_back(&mut self, time: f64, value: f64) {
+        let index = self.pushed;
+        self.pushed += 1;
+        self.values.push_back((time, value));
+
+        if value.is_finite() {
+            self.moments.push(value);
+        } else {
+            self.non_finite += 1;
+        }
+
+        // `NaN` is neither the minimum nor the maximum, as in `f64::min`.
+        if !value.is_nan() {
+            while self.mins.back().is_some_and(|(_, min)| *min >= value) {
+                self.mins.pop_back();
+            }
+            self.mins.push_back((index, value));
+
+            while self.maxs.back().is_some_and(|(_, max)| *max <= value) {
+                self.maxs.pop_back();
+            }
+            self.maxs.push_back((index, value));
+        }
+    }
+
+    fn pop_front(&mut self) {
+        let index = self.pushed - self.values.len() as u64;
+        let value = match self.values.pop_front() {
+            Some((_, value)) => value,
+            None => return,
+        };
+
+        if value.is_finite() {
+            self.moments.pop(value);
+        } else {
+            self.non_finite -= 1;
+        }
+
+        self.popped += 1;
+        if self.popped >= self.values.len() {
+            self.popped = 0;
+            let moments = &mut self.moments;
+            *moments = Moments::default();
+            self.values
+                .iter()
+                .filter(|(_, value)| value.is_finite())
+                .for_each(|(_, value)| moments.push(*value));
+        }
+
+        if self.mins.front().is_some_and(|(front, _)| *front == index) {
+            self.mins.pop_front();
+        }
+        if self.maxs.front().is_some_and(|(front, _)| *front == index) {
+            self.maxs.pop_front();
+        }
+    }
+
+    fn result(&self, aggregate: Aggregate) -> f64 {
+        match aggregate {
+            Aggregate::Min =>
+                self.mins.front().map_or(f64::INFINITY, |(_, min)| *min),
+            Aggregate::Max =>
+                self.maxs.front().map_or(f64::NEG_INFINITY, |(_, max)| *max),
+            // Infinities and `NaN` cannot be removed from the moments, so
+            // the rare windows holding them are scanned.
+            _ if self.non_finite > 0 => {
+                let mut accumulator = Accumulator::new();
+                self.values
+                    .iter()
+                    .for_each(|(_, value)| accumulator.push(*value));
+
+                accumulator.result(aggregate)
+            }
+            Aggregate::Mean => self.moments.mean(),
+            Aggregate::Sum => self.moments.sum,
+            Aggregate::Stddev => self.moments.stddev(),
+        }
+    }
+}
+
+/// Resampling of the records into fixed-length time buckets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resampling {
+    /// The length of the buckets.
+    pub interval: Duration,
+    /// How the values in a bucket are combined.
+    pub aggregate: Aggregate,
+}
+
+/// Records which fell into the same time bucket.
+#[derive(Debug)]
+pub(super) struct Bucket {
+    index: i64,
+    fields: HashMap<String, Accumulator>,
+}
+
+impl Bucket {
+    /// Push the record with the time to the bucket.
+    ///
+    /// If the record belongs to another bucket, the bucket is replaced and the
+    /// record combining the values of the old one is returned.
+    pub(super) fn push(
+        bucket: &mut Option<Bucket>,
+        resampling: &Resampling,
+        time_field: &TimeField,
+        time: f64,
+        record: &Record,
+    ) -> Option<Record<'static>> {
+        let interval = time_field.unit.convert(resampling.interval);
+        let index = (time / interval).floor() as i64;
+
+        let resampled = match bucket {
+            Some(current) if current.index == index => None,
+            _ => bucket
+                .take()
+                .map(|bucket| bucket.into_record(resampling, time_field)),
+        };
+
+        let bucket = bucket.get_or_insert_with(|| Bucket {
+            index,
+            fields: HashMap::new(),
+        });
+        for (field, number) in &record.0 {
+            bucket
+                .fields
+                .entry(field.to_string())
+                .or_insert_with(Accumulator::new)
+                .push(number.as_f64());
+        }
+
+        resampled
+    }
+
+    /// Combine the values in the bucket into a record. The time field is set
+    /// to the start of the bucket.
+    pub(super) fn into_record(
+        self,
+        resampling: &Resampling,
+        time_field: &TimeField,
+    ) -> Record<'static> {
+        let interval = time_field.unit.convert(resampling.interval);
+        let index = self.index;
+
+        let fields = self
+            .fields
+            .into_iter()
+            .map(|(field, accumulator)| {
+                let value = if field == time_field.name {
+                    index as f64 * interval
+                } else {
+                    accumulator.result(resampling.aggregate)
+                };
+
+                (Cow::Owned(field), Number::Float(value))
+            })
+            .collect();
+
+        Record(fields)
+    }
+}
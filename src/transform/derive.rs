@@ -0,0 +1,35 @@
+//! Derived fields.
+
+use std::str::FromStr;
+
+use crate::expr::{Expr, ExprError};
+
+/// A field computed from the other fields of the record.
+#[derive(Debug, Clone)]
+pub struct Derivation {
+    /// The name of the field.
+    pub name: String,
+    /// The expression the field is computed with.
+    pub expr: Expr,
+}
+
+impl FromStr for Derivation {
+    type Err = ExprError;
+
+    /// Parse a derivation written as `name = expr`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = s.find('=').ok_or_else(|| {
+            ExprError::new("expected a derivation written as `name = expr`")
+        })?;
+        let name = s[..index].trim().trim_matches('`');
+
+        if name.is_empty() {
+            return Err(ExprError::new("the derived field has no name"));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            expr: s[index + 1..].parse()?,
+        })
+    }
+}
@@ -0,0 +1,550 @@
+//! Transformations applied to the records between parsing and storing them.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    expr::{is_true, Expr, Scope},
+    parse::record::Record,
+    storage::Number,
+};
+
+pub mod aggregate;
+pub mod derive;
+pub mod select;
+
+use self::aggregate::{Bucket, WindowValues};
+
+pub use self::{
+    aggregate::{Aggregate, Resampling, Rolling, TimeField, TimeUnit, Window},
+    derive::Derivation,
+    select::Rename,
+};
+
+/// The suffix of the names of the datasets holding resampled records.
+pub const RESAMPLED_SUFFIX: &str = ".resampled";
+
+/// Get the name of the dataset holding the resampled records of the dataset.
+fn resampled_dataset(dataset: &str) -> String {
+    format!("{}{}", dataset, RESAMPLED_SUFFIX)
+}
+
+/// Transform error.
+///
+/// Returned when an expression cannot be evaluated with the record, e.g.
+/// because it refers to a missing field.
+#[derive(Debug)]
+pub struct TransformError;
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "transform error")
+    }
+}
+
+/// The fields of the record and of the previous record of the same dataset.
+struct RecordScope<'r, 'a> {
+    record: &'r Record<'a>,
+    previous: Option<&'r HashMap<String, f64>>,
+}
+
+impl<'r, 'a> Scope for RecordScope<'r, 'a> {
+    fn get(&self, field: &str) -> Option<f64> {
+        self.record.0.get(field).map(|number| number.as_f64())
+    }
+
+    fn get_previous(&self, field: &str) -> Option<f64> {
+        self.previous
+            .and_then(|previous| previous.get(field))
+            .cloned()
+    }
+}
+
+/// Transform settings.
+///
+/// Filtering and selection use the names of the fields before renaming.
+#[derive(Debug, Default)]
+pub struct TransformSettings {
+    /// Derived fields, computed in order.
+    pub derivations: Vec<Derivation>,
+    /// A predicate. Records for which it is false are dropped.
+    pub filter: Option<Expr>,
+    /// The fields to keep. `None` means all fields.
+    pub fields: Option<Vec<String>>,
+    /// Renamed fields.
+    pub renames: Vec<Rename>,
+    /// Rolling aggregates, computed after filtering.
+    pub rollings: Vec<Rolling>,
+    /// The field used by time windows and resampling.
+    pub time_field: Option<TimeField>,
+    /// Resampling of the stored records into another dataset.
+    pub resampling: Option<Resampling>,
+}
+
+/// Transformations applied to every record.
+#[derive(Debug, Default)]
+pub struct Transform {
+    derivations: Vec<Derivation>,
+    filter: Option<Expr>,
+    fields: Option<HashSet<String>>,
+    renames: Vec<Rename>,
+    rollings: Vec<Rolling>,
+    time_field: Option<TimeField>,
+    resampling: Option<Resampling>,
+    /// Values in the rolling windows of each dataset.
+    windows: HashMap<String, Vec<WindowValues>>,
+    /// The current time bucket of each dataset.
+    buckets: HashMap<String, Option<Bucket>>,
+    /// Fields of the previous record the expressions refer to.
+    previous_fields: HashSet<String>,
+    /// Values of these fields in the previous record of each dataset.
+    previous: HashMap<String, HashMap<String, f64>>,
+}
+
+impl Transform {
+    /// Create a transform.
+    ///
+    /// The fields are derived first, then the records are filtered, the
+    /// rolling aggregates are computed and the fields are selected and
+    /// renamed. A derivation can use the fields
+    /// derived before it. Derived fields replace the fields with the same
+    /// name.
+    pub fn new(settings: TransformSettings) -> Self {
+        let previous_fields = settings
+            .derivations
+            .iter()
+            .map(|derivation| &derivation.expr)
+            .chain(settings.filter.iter())
+            .flat_map(Expr::previous_fields)
+            .map(str::to_string)
+            .collect();
+
+        Self {
+            derivations: settings.derivations,
+            filter: settings.filter,
+            fields: settings.fields.map(|fields| fields.into_iter().collect()),
+            renames: settings.renames,
+            rollings: settings.rollings,
+            time_field: settings.time_field,
+            resampling: settings.resampling,
+            windows: HashMap::new(),
+            buckets: HashMap::new(),
+            previous_fields,
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Transform the record of the dataset.
+    ///
+    /// Returns `None` if the record has been filtered out. `prev` refers to
+    /// the previous record of the dataset even if it has been filtered out.
+    pub fn apply<'a>(
+        &mut self,
+        dataset: &str,
+        mut record: Record<'a>,
+    ) -> Result<Option<Record<'a>>, TransformError> {
+        for derivation in &self.derivations {
+            let scope = RecordScope {
+                record: &record,
+                previous: self.previous.get(dataset),
+            };
+            let value =
+                derivation.expr.eval(&scope).map_err(|_| TransformError)?;
+
+            record.0.insert(
+                Cow::Owned(derivation.name.clone()),
+                Number::Float(value),
+            );
+        }
+
+        let is_kept = if let Some(filter) = &self.filter {
+            let scope = RecordScope {
+                record: &record,
+                previous: self.previous.get(dataset),
+            };
+
+            is_true(filter.eval(&scope).map_err(|_| TransformError)?)
+        } else {
+            true
+        };
+
+        if !self.previous_fields.is_empty() {
+            let values = self
+                .previous_fields
+                .iter()
+                .filter_map(|field| {
+                    record
+                        .0
+                        .get(field.as_str())
+                        .map(|number| (field.clone(), number.as_f64()))
+                })
+                .collect();
+
+            self.previous.insert(dataset.to_string(), values);
+        }
+
+        if !is_kept {
+            return Ok(None);
+        }
+
+        if !self.rollings.is_empty() {
+            let rollings = &self.rollings;
+            let windows =
+                self.windows.entry(dataset.to_string()).or_insert_with(|| {
+                    rollings.iter().map(|_| WindowValues::default()).collect()
+                });
+
+            for (rolling, window) in rollings.iter().zip(windows) {
+                let value = window
+                    .push(rolling, self.time_field.as_ref(), &record)
+                    .ok_or(TransformError)?;
+
+                record.0.insert(
+                    Cow::Owned(rolling.name.clone()),
+                    Number::Float(value),
+                );
+            }
+        }
+
+        if let Some(fields) = &self.fields {
+            record.0.retain(|field, _| fields.contains(field.as_ref()));
+        }
+
+        for rename in &self.renames {
+            if let Some(number) = record.0.remove(rename.from.as_str()) {
+                record.0.insert(Cow::Owned(rename.to.clone()), number);
+            }
+        }
+
+        Ok(Some(record))
+    }
+
+    /// Check whether the transform leaves the records as they are and does
+    /// not resample them.
+    pub fn is_empty(&self) -> bool {
+        self.derivations.is_empty()
+            && self.filter.is_none()
+            && self.fields.is_none()
+            && self.renames.is_empty()
+            && self.rollings.is_empty()
+            && self.resampling.is_none()
+    }
+
+    /// Push the stored record of the dataset to its time bucket.
+    ///
+    /// Returns the resampled record and its dataset once the record starts
+    /// another bucket. Fails if the record lacks the time field.
+    pub fn resample(
+        &mut self,
+        dataset: &str,
+        record: &Record,
+    ) -> Result<Option<(String, Record<'static>)>, TransformError> {
+        let (resampling, time_field) =
+            match (&self.resampling, &self.time_field) {
+                (Some(resampling), Some(time_field)) =>
+                    (resampling, time_field),
+                _ => return Ok(None),
+            };
+        let time = time_field.get(record).ok_or(TransformError)?;
+        let bucket = self.buckets.entry(dataset.to_string()).or_default();
+
+        Ok(Bucket::push(bucket, resampling, time_field, time, record)
+            .map(|record| (resampled_dataset(dataset), record)))
+    }
+
+    /// Take the records of the last, incomplete time buckets of all the
+    /// datasets, e.g. once all the inputs have ended.
+    pub fn flush_resampled(&mut self) -> Vec<(String, Record<'static>)> {
+        let (resampling, time_field) =
+            match (&self.resampling, &self.time_field) {
+                (Some(resampling), Some(time_field)) =>
+                    (resampling, time_field),
+                _ => return vec![],
+            };
+
+        let mut records = self
+            .buckets
+            .drain()
+            .filter_map(|(dataset, bucket)| {
+                bucket.map(|bucket| {
+                    (
+                        resampled_dataset(&dataset),
+                        bucket.into_record(resampling, time_field),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        records.sort_by(|a, b| a.0.cmp(&b.0));
+
+        records
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::pretty_assertions::assert_eq;
+
+    use std::time::Duration;
+
+    use super::*;
+
+    fn record(fields: &[(&'static str, Number)]) -> Record<'static> {
+        Record(
+            fields
+                .iter()
+                .map(|(field, number)| (Cow::Borrowed(*field), *number))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_derive_fields() {
+        let mut transform = Transform::new(TransformSettings {
+            derivations: vec![
+                "energy = 0.5 * mass * v^2".parse().unwrap(),
+                "dv = v - prev(v)".parse().unwrap(),
+                "v = v * 2".parse().unwrap(),
+            ],
+            ..TransformSettings::default()
+        });
+
+        let first =
+            record(&[("mass", Number::Int(2)), ("v", Number::Float(3.0))]);
+        let first = transform.apply("a", first).unwrap().unwrap();
+
+        assert_eq!(first.0["energy"], Number::Float(9.0));
+        assert_eq!(first.0["v"], Number::Float(6.0));
+        match first.0["dv"] {
+            Number::Float(dv) => assert!(dv.is_nan()),
+            _ => panic!("a derived field is not a float"),
+        }
+
+        // The previous values are kept separately for each dataset.
+        let other =
+            record(&[("mass", Number::Int(1)), ("v", Number::Float(0.0))]);
+        transform.apply("b", other).unwrap();
+
+        let second =
+            record(&[("mass", Number::Int(2)), ("v", Number::Float(4.0))]);
+        let second = transform.apply("a", second).unwrap().unwrap();
+
+        // `prev(v)` is the value of `v` after all derivations.
+        assert_eq!(second.0["dv"], Number::Float(-2.0));
+    }
+
+    #[test]
+    fn test_derive_missing_field() {
+        let mut transform = Transform::new(TransformSettings {
+            derivations: vec!["y = x + 1".parse().unwrap()],
+            ..TransformSettings::default()
+        });
+        let record = record(&[("z", Number::Int(1))]);
+
+        assert!(transform.apply("a", record).is_err());
+    }
+
+    #[test]
+    fn test_filter_select_and_rename() {
+        let mut transform = Transform::new(TransformSettings {
+            filter: Some("x > prev(x)".parse().unwrap()),
+            fields: Some(vec!["x".to_string(), "y".to_string()]),
+            renames: vec!["y=z".parse().unwrap()],
+            ..TransformSettings::default()
+        });
+
+        let records = [1, 3, 2, 4]
+            .iter()
+            .map(|x| {
+                let record = record(&[
+                    ("x", Number::Int(*x)),
+                    ("y", Number::Int(x * 10)),
+                    ("w", Number::Int(0)),
+                ]);
+
+                transform.apply("a", record).unwrap().map(|record| {
+                    let mut fields = record.0.into_iter().collect::<Vec<_>>();
+                    fields.sort_by(|a, b| a.0.cmp(&b.0));
+
+                    fields
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // The first record is dropped because `prev(x)` is `NaN`.
+        assert_eq!(
+            records,
+            vec![
+                None,
+                Some(vec![
+                    (Cow::Borrowed("x"), Number::Int(3)),
+                    (Cow::Borrowed("z"), Number::Int(30)),
+                ]),
+                None,
+                Some(vec![
+                    (Cow::Borrowed("x"), Number::Int(4)),
+                    (Cow::Borrowed("z"), Number::Int(40)),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rolling_aggregates() {
+        let mut transform = Transform::new(TransformSettings {
+            rollings: vec![
+                "x_mean = mean(x, 2)".parse().unwrap(),
+                "x_max = max(x, 1500ms)".parse().unwrap(),
+                "x_std = stddev(x, 3)".parse().unwrap(),
+            ],
+            time_field: Some(TimeField {
+                name: "t".to_string(),
+                unit: TimeUnit::Seconds,
+            }),
+            ..TransformSettings::default()
+        });
+
+        let records = [(0, 4.0), (1, 2.0), (2, 0.0), (3, 1.0)]
+            .iter()
+            .map(|(t, x)| {
+                let record =
+                    record(&[("t", Number::Int(*t)), ("x", Number::Float(*x))]);
+                let record = transform.apply("a", record).unwrap().unwrap();
+
+                (
+                    record.0["x_mean"],
+                    record.0["x_max"],
+                    record.0["x_std"].as_f64(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(records[0].0, Number::Float(4.0));
+        assert_eq!(records[1].0, Number::Float(3.0));
+        assert_eq!(records[3].0, Number::Float(0.5));
+        assert_eq!(records[1].1, Number::Float(4.0));
+        assert_eq!(records[2].1, Number::Float(2.0));
+        assert_eq!(records[3].1, Number::Float(1.0));
+        assert_eq!(records[0].2, 0.0);
+        assert!((records[2].2 - (8.0f64 / 3.0).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rolling_aggregates_over_long_input() {
+        let mut transform = Transform::new(TransformSettings {
+            rollings: vec![
+                "x_sum = sum(x, 3)".parse().unwrap(),
+                "x_min = min(x, 3)".parse().unwrap(),
+                "x_std = stddev(x, 3)".parse().unwrap(),
+            ],
+            ..TransformSettings::default()
+        });
+
+        // Large values with small deviations, then a `NaN` leaving the window.
+        let values = (0..10_000)
+            .map(|i| 1e9 + f64::from(i % 3))
+            .chain(vec![f64::NAN, 5.0, 4.0, 3.0, 2.0, 1.0, 0.0, -1.0])
+            .collect::<Vec<_>>();
+        let records = values
+            .iter()
+            .map(|x| {
+                let record = record(&[("x", Number::Float(*x))]);
+                let record = transform.apply("a", record).unwrap().unwrap();
+
+                (
+                    record.0["x_sum"].as_f64(),
+                    record.0["x_min"].as_f64(),
+                    record.0["x_std"].as_f64(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let (sum, min, std) = records[9_999];
+        assert_eq!(sum, 3e9 + 3.0);
+        assert_eq!(min, 1e9);
+        assert!((std - (2.0f64 / 3.0).sqrt()).abs() < 1e-6);
+
+        assert!(records[10_001].0.is_nan());
+        assert_eq!(records[10_001].1, 5.0);
+        assert_eq!(records[10_003].0, 12.0);
+        assert_eq!(records[10_004].1, 2.0);
+
+        let (sum, min, std) = records[10_007];
+        assert_eq!((sum, min), (0.0, -1.0));
+        assert!((std - (2.0f64 / 3.0).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_resample() {
+        let mut transform = Transform::new(TransformSettings {
+            time_field: Some(TimeField {
+                name: "t".to_string(),
+                unit: TimeUnit::Milliseconds,
+            }),
+            resampling: Some(Resampling {
+                interval: Duration::from_millis(100),
+                aggregate: Aggregate::Mean,
+            }),
+            ..TransformSettings::default()
+        });
+
+        let resampled = [(10, 1), (50, 3), (120, 5), (330, 7), (340, 9)]
+            .iter()
+            .filter_map(|(t, x)| {
+                let record =
+                    record(&[("t", Number::Int(*t)), ("x", Number::Int(*x))]);
+
+                transform.resample("a", &record).unwrap()
+            })
+            .map(|(dataset, record)| (dataset, record.0["t"], record.0["x"]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            resampled,
+            vec![
+                (
+                    "a.resampled".to_string(),
+                    Number::Float(0.0),
+                    Number::Float(2.0)
+                ),
+                (
+                    "a.resampled".to_string(),
+                    Number::Float(100.0),
+                    Number::Float(5.0)
+                ),
+            ]
+        );
+
+        // The last bucket is incomplete.
+        let flushed = transform.flush_resampled();
+
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0].0, "a.resampled");
+        assert_eq!(flushed[0].1 .0["t"], Number::Float(300.0));
+        assert_eq!(flushed[0].1 .0["x"], Number::Float(8.0));
+        assert!(transform.flush_resampled().is_empty());
+
+        let record = record(&[("x", Number::Int(1))]);
+
+        assert!(transform.resample("a", &record).is_err());
+    }
+
+    #[test]
+    fn test_parse_derivation() {
+        let derivation = "`x 2` = x * 2".parse::<Derivation>().unwrap();
+
+        assert_eq!(derivation.name, "x 2");
+        assert!("x * 2".parse::<Derivation>().is_err());
+        assert!(" = x".parse::<Derivation>().is_err());
+        assert!("x=".parse::<Rename>().is_err());
+
+        let rolling = "m = mean(`a, b`, 5s)".parse::<Rolling>().unwrap();
+        assert_eq!(rolling.field, "a, b");
+        assert_eq!(rolling.window, Window::Duration(Duration::from_secs(5)));
+        assert!("m = median(x, 5)".parse::<Rolling>().is_err());
+        assert!("m = mean(x, 0)".parse::<Rolling>().is_err());
+        assert!("m = mean(x)".parse::<Rolling>().is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! Field selection and renaming.
+
+use std::str::FromStr;
+
+use crate::expr::ExprError;
+
+/// A field renamed before the record is stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    /// The name of the field in the parsed record.
+    pub from: String,
+    /// The name the field is stored with.
+    pub to: String,
+}
+
+impl FromStr for Rename {
+    type Err = ExprError;
+
+    /// Parse a rename written as `old=new`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = s.find('=').ok_or_else(|| {
+            ExprError::new("expected a rename written as `old=new`")
+        })?;
+        let (from, to) = (s[..index].trim(), s[index + 1..].trim());
+
+        if from.is_empty() || to.is_empty() {
+            return Err(ExprError::new(
+                "expected a rename written as `old=new`",
+            ));
+        }
+
+        Ok(Self {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+}